@@ -0,0 +1,237 @@
+// The command line, parsed in one place before the terminal is touched so
+// --help, --version and mistakes can be reported on the normal screen.
+// The forms that matter for use as $EDITOR / $VISUAL are what git, crontab
+// and friends pass: a `+N` or `+/pattern` before the file, `-` for
+// standard input, and `--` ahead of a file name that starts with + or -.
+
+use crate::tags::Address;
+
+pub const USAGE: &str = "\
+usage: rte [options] [+N | +/pattern] [--] [file | -]
+       rte --diff OLD NEW
+
+  +N               start on line N (+ alone: the last line)
+  +/pattern        start on the first line containing pattern
+  -                read the text from standard input
+  -R, --readonly   mark the buffer read-only
+  --session NAME   restore session NAME and save it on quit
+  --restore        the same, with the session named after this directory
+  --diff OLD NEW   compare two files side by side
+  -h, --help       show this help
+  -V, --version    show the version";
+
+pub enum Input {
+    File(String),
+    Stdin,
+    Diff(String, String),
+}
+
+pub enum Session {
+    Named(String),
+    /// Named after the current directory.
+    Restore,
+}
+
+#[derive(Default)]
+pub struct Args {
+    pub input: Option<Input>,
+    pub start: Option<Address>,
+    pub readonly: bool,
+    pub session: Option<Session>,
+}
+
+pub enum Command {
+    Run(Args),
+    Help,
+    Version,
+}
+
+/// `args` without the program name.
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+    let mut options_done = false;
+    while let Some(arg) = args.next() {
+        if options_done {
+            set_input(&mut parsed, Input::File(arg))?;
+            continue;
+        }
+        match arg.as_str() {
+            "--" => options_done = true,
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "-R" | "--readonly" => parsed.readonly = true,
+            "--restore" => parsed.session = Some(Session::Restore),
+            "--session" => match args.next() {
+                Some(name) => parsed.session = Some(Session::Named(name)),
+                None => return Err(String::from("--session needs a name")),
+            },
+            "--diff" => match (args.next(), args.next()) {
+                (Some(left), Some(right)) => set_input(&mut parsed, Input::Diff(left, right))?,
+                _ => return Err(String::from("--diff needs two files")),
+            },
+            "-" => set_input(&mut parsed, Input::Stdin)?,
+            _ if arg.starts_with('+') => parsed.start = Some(start(&arg[1..])?),
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+            _ => set_input(&mut parsed, Input::File(arg))?,
+        }
+    }
+    if parsed.start.is_some() && matches!(parsed.input, Some(Input::Diff(..))) {
+        return Err(String::from("a starting line can't be given with --diff"));
+    }
+    Ok(Command::Run(parsed))
+}
+
+fn set_input(parsed: &mut Args, input: Input) -> Result<(), String> {
+    if parsed.input.is_some() {
+        return Err(String::from("only one file can be opened"));
+    }
+    parsed.input = Some(input);
+    Ok(())
+}
+
+/// What follows the `+`: a line number, a `/pattern` (anchored with ^ and
+/// $ as in vi), or nothing for the last line.
+fn start(position: &str) -> Result<Address, String> {
+    if position.is_empty() {
+        return Ok(Address::Line(usize::MAX));
+    }
+    if let Some(pattern) = position.strip_prefix('/') {
+        let (start, pattern) = match pattern.strip_prefix('^') {
+            Some(pattern) => (true, pattern),
+            None => (false, pattern),
+        };
+        let (end, pattern) = match pattern.strip_suffix('$') {
+            Some(pattern) => (true, pattern),
+            None => (false, pattern),
+        };
+        return Ok(Address::Pattern {
+            text: pattern.to_string(),
+            start,
+            end,
+        });
+    }
+    match position.parse::<usize>() {
+        Ok(line) => Ok(Address::Line(line)),
+        Err(_) => Err(format!("not a line number or /pattern: +{}", position)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(args: &[&str]) -> Args {
+        match parse(args.iter().map(|arg| arg.to_string())) {
+            Ok(Command::Run(args)) => args,
+            Ok(_) => panic!("{:?} asked for help or the version", args),
+            Err(error) => panic!("{:?}: {}", args, error),
+        }
+    }
+
+    fn error(args: &[&str]) -> String {
+        match parse(args.iter().map(|arg| arg.to_string())) {
+            Err(error) => error,
+            Ok(_) => panic!("{:?} was accepted", args),
+        }
+    }
+
+    fn file(args: &Args) -> Option<&str> {
+        match &args.input {
+            Some(Input::File(file)) => Some(file),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn line_before_the_file() {
+        let args = run(&["+12", "notes.txt"]);
+        assert_eq!(file(&args), Some("notes.txt"));
+        assert!(matches!(args.start, Some(Address::Line(12))));
+    }
+
+    #[test]
+    fn pattern_with_anchors() {
+        let args = run(&["+/^fn main$", "main.rs"]);
+        match args.start {
+            Some(Address::Pattern { text, start, end }) => {
+                assert_eq!(text, "fn main");
+                assert!(start && end);
+            }
+            _ => panic!("no pattern"),
+        }
+        let args = run(&["+/TODO", "main.rs"]);
+        assert!(matches!(args.start, Some(Address::Pattern { start: false, end: false, .. })));
+    }
+
+    #[test]
+    fn bare_plus_is_the_last_line() {
+        let args = run(&["+", "log"]);
+        assert!(matches!(args.start, Some(Address::Line(usize::MAX))));
+    }
+
+    #[test]
+    fn separator_makes_names_of_plus_and_minus() {
+        let args = run(&["--", "+file"]);
+        assert_eq!(file(&args), Some("+file"));
+        assert!(args.start.is_none());
+        assert_eq!(file(&run(&["--", "-R"])), Some("-R"));
+        assert_eq!(file(&run(&["--", "-"])), Some("-"));
+    }
+
+    #[test]
+    fn dash_reads_stdin() {
+        let args = run(&["+3", "-"]);
+        assert!(matches!(args.input, Some(Input::Stdin)));
+        assert!(matches!(args.start, Some(Address::Line(3))));
+    }
+
+    #[test]
+    fn readonly_either_way() {
+        assert!(run(&["-R", "file"]).readonly);
+        assert!(run(&["file", "--readonly"]).readonly);
+        assert!(!run(&["file"]).readonly);
+    }
+
+    #[test]
+    fn nothing_given() {
+        let args = run(&[]);
+        assert!(args.input.is_none() && args.start.is_none() && args.session.is_none());
+    }
+
+    #[test]
+    fn help_and_version_win() {
+        assert!(matches!(parse(vec![String::from("file"), String::from("--help")]), Ok(Command::Help)));
+        assert!(matches!(parse(vec![String::from("-V")]), Ok(Command::Version)));
+    }
+
+    #[test]
+    fn one_file_only() {
+        assert_eq!(error(&["a", "b"]), "only one file can be opened");
+        assert_eq!(error(&["a", "-"]), "only one file can be opened");
+    }
+
+    #[test]
+    fn diff_takes_two_files_and_no_start() {
+        match run(&["--diff", "old", "new"]).input {
+            Some(Input::Diff(left, right)) => assert_eq!((left.as_str(), right.as_str()), ("old", "new")),
+            _ => panic!("not a diff"),
+        }
+        assert_eq!(error(&["+3", "--diff", "old", "new"]), "a starting line can't be given with --diff");
+        assert_eq!(error(&["--diff", "old"]), "--diff needs two files");
+    }
+
+    #[test]
+    fn sessions() {
+        assert!(matches!(run(&["--session", "work", "file"]).session, Some(Session::Named(name)) if name == "work"));
+        assert!(matches!(run(&["--restore"]).session, Some(Session::Restore)));
+        assert_eq!(error(&["--session"]), "--session needs a name");
+    }
+
+    #[test]
+    fn mistakes() {
+        assert_eq!(error(&["-x", "file"]), "unknown option -x");
+        assert_eq!(error(&["--frobnicate"]), "unknown option --frobnicate");
+        assert_eq!(error(&["+12abc", "file"]), "not a line number or /pattern: +12abc");
+    }
+}
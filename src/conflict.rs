@@ -0,0 +1,193 @@
+/// Git's default `conflict-marker-size`.
+const MARKER_SIZE: usize = 7;
+
+#[derive(Clone, Copy)]
+pub enum Section {
+    Marker,
+    Ours,
+    /// The common ancestor, with `merge.conflictStyle = diff3`.
+    Base,
+    Theirs,
+}
+
+/// Rows of one `<<<<<<<` ... `>>>>>>>` region.
+pub struct Conflict {
+    pub start: usize,
+    pub base: Option<usize>,
+    pub separator: usize,
+    pub end: usize,
+}
+
+impl Conflict {
+    pub fn section(&self, at: usize) -> Section {
+        if at == self.start || Some(at) == self.base || at == self.separator || at == self.end {
+            Section::Marker
+        } else if at > self.separator {
+            Section::Theirs
+        } else if self.base.is_some_and(|base| at > base) {
+            Section::Base
+        } else {
+            Section::Ours
+        }
+    }
+}
+
+/// The conflict containing row `at`, if any. `conflicts` is in row order.
+pub fn at(conflicts: &[Conflict], at: usize) -> Option<&Conflict> {
+    let index = conflicts.partition_point(|conflict| conflict.end < at);
+    conflicts.get(index).filter(|conflict| conflict.start <= at)
+}
+
+/// How many of `ch` the line is made of up to the first space, if it's a
+/// marker made of at least MARKER_SIZE of them.
+fn marker(line: &str, ch: char) -> Option<usize> {
+    let line = line.trim_end_matches('\r');
+    let rest = line.trim_start_matches(ch);
+    let size = line.len() - rest.len();
+    let valid = size >= MARKER_SIZE && (rest.is_empty() || rest.starts_with(' '));
+    // nothing may follow the separator
+    let valid = valid && (ch != '=' || rest.trim().is_empty());
+    valid.then_some(size)
+}
+
+/// Finds the conflict regions among `lines`. The closing markers have to be
+/// as long as the opening one, so a file whose conflicted content itself
+/// contains markers (git lengthens the outer ones then) isn't cut short. A
+/// region that's never closed is ignored, and a new opening marker inside
+/// one abandons it.
+pub fn scan<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    let mut open: Option<(usize, Conflict)> = None;
+    for (at, line) in lines.enumerate() {
+        let opening = marker(line, '<');
+        let nested = matches!((&open, opening), (Some((size, _)), Some(opening)) if *size != opening);
+        if let (Some(size), false) = (opening, nested) {
+            if let Some((_, conflict)) = &open {
+                log::debug!("conflict at row {} is never closed", conflict.start + 1);
+            }
+            let conflict = Conflict {
+                start: at,
+                base: None,
+                separator: 0,
+                end: 0,
+            };
+            open = Some((size, conflict));
+            continue;
+        }
+        let (size, conflict) = match &mut open {
+            Some((size, conflict)) => (*size, conflict),
+            None => continue,
+        };
+        let in_ours = conflict.separator == 0;
+        if in_ours && conflict.base.is_none() && marker(line, '|') == Some(size) {
+            conflict.base = Some(at);
+        } else if in_ours && marker(line, '=') == Some(size) {
+            conflict.separator = at;
+        } else if !in_ours && marker(line, '>') == Some(size) {
+            conflict.end = at;
+            conflicts.extend(open.take().map(|(_, conflict)| conflict));
+        }
+    }
+    if let Some((_, conflict)) = open {
+        log::debug!("conflict at row {} is never closed", conflict.start + 1);
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (start, base, separator, end) of each conflict in `text`.
+    fn rows(text: &str) -> Vec<(usize, Option<usize>, usize, usize)> {
+        scan(text.lines())
+            .iter()
+            .map(|conflict| (conflict.start, conflict.base, conflict.separator, conflict.end))
+            .collect()
+    }
+
+    #[test]
+    fn plain_and_diff3() {
+        let text = "a\n<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> branch\nb\n";
+        assert_eq!(rows(text), vec![(1, None, 3, 5)]);
+        let text = "<<<<<<< HEAD\nours\n||||||| base\nold\n=======\ntheirs\n>>>>>>> branch\n";
+        assert_eq!(rows(text), vec![(0, Some(2), 4, 6)]);
+    }
+
+    #[test]
+    fn crlf_markers() {
+        assert_eq!(rows("<<<<<<< HEAD\r\nours\r\n=======\r\ntheirs\r\n>>>>>>> b\r\n"), vec![(0, None, 2, 4)]);
+    }
+
+    #[test]
+    fn not_markers() {
+        // too short, text right after the characters, text after the separator
+        assert_eq!(rows("<<<<<< HEAD\nours\n======\ntheirs\n>>>>>> b\n"), vec![]);
+        assert_eq!(rows("<<<<<<<HEAD\nours\n=======\ntheirs\n>>>>>>> b\n"), vec![]);
+        assert_eq!(rows("<<<<<<< HEAD\nours\n======= not\ntheirs\n>>>>>>> b\n"), vec![]);
+    }
+
+    #[test]
+    fn closing_markers_match_the_opening_size() {
+        let text = "<<<<<<<<< HEAD\n<<<<<<< inner\n=======\n>>>>>>> inner\n=========\ntheirs\n>>>>>>>>> b\n";
+        assert_eq!(rows(text), vec![(0, None, 4, 6)]);
+    }
+
+    #[test]
+    fn unterminated_regions_are_dropped() {
+        assert_eq!(rows("<<<<<<< HEAD\nours\n=======\ntheirs\n"), vec![]);
+        // a new opening marker of the same size abandons the open region
+        let text = "<<<<<<< HEAD\nours\n<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> b\n";
+        assert_eq!(rows(text), vec![(2, None, 4, 6)]);
+    }
+
+    #[test]
+    fn closing_before_the_separator_is_content() {
+        let text = "<<<<<<< HEAD\n>>>>>>> early\n=======\ntheirs\n>>>>>>> b\n";
+        assert_eq!(rows(text), vec![(0, None, 2, 4)]);
+        // and so is a second base marker
+        let text = "<<<<<<< HEAD\n||||||| one\n||||||| two\n=======\n>>>>>>> b\n";
+        assert_eq!(rows(text), vec![(0, Some(1), 3, 4)]);
+    }
+
+    #[test]
+    fn sections() {
+        let conflict = Conflict {
+            start: 0,
+            base: Some(2),
+            separator: 4,
+            end: 6,
+        };
+        let sections: Vec<&str> = (0..=6)
+            .map(|at| match conflict.section(at) {
+                Section::Marker => "marker",
+                Section::Ours => "ours",
+                Section::Base => "base",
+                Section::Theirs => "theirs",
+            })
+            .collect();
+        assert_eq!(sections, ["marker", "ours", "marker", "base", "marker", "theirs", "marker"]);
+        let conflict = Conflict { base: None, ..conflict };
+        assert!(matches!(conflict.section(3), Section::Ours));
+    }
+
+    #[test]
+    fn conflict_at_a_row() {
+        let conflicts = regions(&[(2, 4), (10, 14)]);
+        let rows = [0, 2, 4, 5, 9, 10, 12, 14, 15];
+        let starts: Vec<Option<usize>> = rows.iter().map(|&row| at(&conflicts, row).map(|conflict| conflict.start)).collect();
+        assert_eq!(starts, [None, Some(2), Some(2), None, None, Some(10), Some(10), Some(10), None]);
+        assert!(at(&[], 0).is_none());
+    }
+
+    fn regions(rows: &[(usize, usize)]) -> Vec<Conflict> {
+        rows.iter()
+            .map(|&(start, end)| Conflict {
+                start,
+                base: None,
+                separator: start + 1,
+                end,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,264 @@
+// Line diff using Myers' O(ND) algorithm, in its linear-space form.
+
+use std::cmp;
+
+/// How far apart (in edits, for each of the two searches) the parts of a
+/// diff may be before they're given up on and replaced as a whole, which
+/// bounds the time unrelated files take.
+const MAX_COST: isize = 1024;
+
+/// A run of `old_len` lines at `old_start` replaced by `new_len` lines at
+/// `new_start`. Either length may be zero for pure insertions/deletions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+}
+
+enum Edit {
+    Equal,
+    Delete,
+    Insert,
+}
+
+pub fn diff_lines<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Hunk> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let script = edit_script(&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    let mut hunks = Vec::new();
+    let mut pending: Option<Hunk> = None;
+    let (mut old_index, mut new_index) = (prefix, prefix);
+    for edit in script {
+        if let Edit::Equal = edit {
+            hunks.extend(pending.take());
+            old_index += 1;
+            new_index += 1;
+            continue;
+        }
+        let hunk = pending.get_or_insert(Hunk {
+            old_start: old_index,
+            old_len: 0,
+            new_start: new_index,
+            new_len: 0,
+        });
+        if let Edit::Delete = edit {
+            hunk.old_len += 1;
+            old_index += 1;
+        } else {
+            hunk.new_len += 1;
+            new_index += 1;
+        }
+    }
+    hunks.extend(pending);
+    hunks
+}
+
+fn edit_script<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Edit> {
+    let mut script = Vec::with_capacity(old.len() + new.len());
+    let size = 2 * cmp::min((old.len() + new.len()).div_ceil(2), MAX_COST as usize) + 3;
+    let (mut forward, mut backward) = (vec![0isize; size], vec![0isize; size]);
+    compare(old, new, &mut forward, &mut backward, &mut script);
+    script
+}
+
+/// The linear-space variant: finds the middle snake of the shortest edit
+/// path, then diffs the parts before and after it, so only two rows of
+/// furthest-reaching x are ever kept.
+fn compare<T: PartialEq>(old: &[T], new: &[T], forward: &mut [isize], backward: &mut [isize], script: &mut Vec<Edit>) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    script.extend((0..prefix).map(|_| Edit::Equal));
+    let (old, new) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+    if old.is_empty() {
+        script.extend(new.iter().map(|_| Edit::Insert));
+    } else if new.is_empty() {
+        script.extend(old.iter().map(|_| Edit::Delete));
+    } else {
+        // with no common ends the path has at least two edits, so both
+        // halves are smaller than the whole
+        match middle_snake(old, new, forward, backward) {
+            Some((x, y, u, v)) => {
+                compare(&old[..x], &new[..y], forward, backward, script);
+                script.extend((x..u).map(|_| Edit::Equal));
+                compare(&old[u..], &new[v..], forward, backward, script);
+            }
+            None => {
+                script.extend(old.iter().map(|_| Edit::Delete));
+                script.extend(new.iter().map(|_| Edit::Insert));
+            }
+        }
+    }
+    script.extend((0..suffix).map(|_| Edit::Equal));
+}
+
+/// The start and end (x, y, u, v) of the snake where the forward and
+/// backward searches meet. The backward search runs on the reversed
+/// sequences, so its diagonal k' is the forward diagonal delta - k. None
+/// when that's more than MAX_COST edits from either end.
+fn middle_snake<T: PartialEq>(
+    old: &[T],
+    new: &[T],
+    forward: &mut [isize],
+    backward: &mut [isize],
+) -> Option<(usize, usize, usize, usize)> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    let max = cmp::min((n + m + 1) / 2, MAX_COST);
+    let offset = max + 1;
+    let at = |k: isize| (k + offset) as usize;
+    forward[at(1)] = 0;
+    backward[at(1)] = 0;
+    for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && forward[at(k - 1)] < forward[at(k + 1)]) {
+                forward[at(k + 1)]
+            } else {
+                forward[at(k - 1)] + 1
+            };
+            let (start_x, start_y) = (x, x - k);
+            let mut y = start_y;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[at(k)] = x;
+            let reverse_k = delta - k;
+            if odd && (-(d - 1)..=d - 1).contains(&reverse_k) && x + backward[at(reverse_k)] >= n {
+                return Some((start_x as usize, start_y as usize, x as usize, y as usize));
+            }
+        }
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && backward[at(k - 1)] < backward[at(k + 1)]) {
+                backward[at(k + 1)]
+            } else {
+                backward[at(k - 1)] + 1
+            };
+            let (start_x, start_y) = (x, x - k);
+            let mut y = start_y;
+            while x < n && y < m && old[(n - x - 1) as usize] == new[(m - y - 1) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[at(k)] = x;
+            let forward_k = delta - k;
+            if !odd && (-d..=d).contains(&forward_k) && x + forward[at(forward_k)] >= n {
+                return Some(((n - x) as usize, (m - y) as usize, (n - start_x) as usize, (m - start_y) as usize));
+            }
+        }
+    }
+    // they always meet by d = (n + m) / 2, so only the cap gets here
+    None
+}
+
+/// Where old row `row` ended up after the edits in `hunks`: shifted past
+/// insertions and deletions above it, kept in place if its line was
+/// changed, gone if it was deleted.
+pub fn map_row(hunks: &[Hunk], row: usize) -> Option<usize> {
+    let mut shift = 0isize;
+    for hunk in hunks {
+        if row < hunk.old_start {
+            break;
+        }
+        if row < hunk.old_start + hunk.old_len {
+            let offset = row - hunk.old_start;
+            return (offset < hunk.new_len).then_some(hunk.new_start + offset);
+        }
+        shift = (hunk.new_start + hunk.new_len) as isize - (hunk.old_start + hunk.old_len) as isize;
+    }
+    Some((row as isize + shift) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Applies `hunks` to `old`, which has to give back `new`.
+    fn apply(old: &[u32], new: &[u32], hunks: &[Hunk]) -> Vec<u32> {
+        let mut result = Vec::new();
+        let mut at = 0;
+        for hunk in hunks {
+            result.extend_from_slice(&old[at..hunk.old_start]);
+            result.extend_from_slice(&new[hunk.new_start..hunk.new_start + hunk.new_len]);
+            at = hunk.old_start + hunk.old_len;
+        }
+        result.extend_from_slice(&old[at..]);
+        result
+    }
+
+    #[test]
+    fn finds_a_change_in_the_middle() {
+        let old = ["a", "b", "c", "d"];
+        let new = ["a", "x", "c", "d", "e"];
+        let hunks = diff_lines(&old, &new);
+        assert_eq!(
+            hunks,
+            vec![
+                Hunk { old_start: 1, old_len: 1, new_start: 1, new_len: 1 },
+                Hunk { old_start: 4, old_len: 0, new_start: 4, new_len: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn edits_are_minimal() {
+        let old: Vec<u32> = "abcabba".bytes().map(u32::from).collect();
+        let new: Vec<u32> = "cbabac".bytes().map(u32::from).collect();
+        let script = edit_script(&old, &new);
+        let edits = script.iter().filter(|edit| !matches!(edit, Edit::Equal)).count();
+        assert_eq!(edits, 5);
+        assert_eq!(apply(&old, &new, &diff_lines(&old, &new)), new);
+    }
+
+    #[test]
+    fn reproduces_the_new_lines() {
+        // a cheap generator, so the inputs vary without a rand dependency
+        let mut seed = 7u32;
+        let mut next = move |range: u32| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (seed >> 16) % range
+        };
+        for _ in 0..200 {
+            let old: Vec<u32> = (0..next(40)).map(|_| next(4)).collect();
+            let new: Vec<u32> = (0..next(40)).map(|_| next(4)).collect();
+            assert_eq!(apply(&old, &new, &diff_lines(&old, &new)), new);
+        }
+    }
+
+    #[test]
+    fn unrelated_inputs_are_one_hunk() {
+        let old: Vec<u32> = (0..6000).collect();
+        let new: Vec<u32> = (6000..12000).collect();
+        let hunks = diff_lines(&old, &new);
+        assert_eq!(hunks, vec![Hunk { old_start: 0, old_len: 6000, new_start: 0, new_len: 6000 }]);
+    }
+
+    #[test]
+    fn gives_up_on_distant_parts_but_stays_correct() {
+        let old: Vec<u32> = (0..5000).map(|at| at % 7).collect();
+        let new: Vec<u32> = (0..5000).map(|at| at % 11).collect();
+        assert_eq!(apply(&old, &new, &diff_lines(&old, &new)), new);
+    }
+
+    #[test]
+    fn maps_rows_past_hunks() {
+        let hunks = [Hunk { old_start: 2, old_len: 2, new_start: 2, new_len: 1 }];
+        assert_eq!(map_row(&hunks, 1), Some(1));
+        assert_eq!(map_row(&hunks, 2), Some(2));
+        assert_eq!(map_row(&hunks, 3), None);
+        assert_eq!(map_row(&hunks, 5), Some(4));
+    }
+}
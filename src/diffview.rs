@@ -0,0 +1,115 @@
+use crate::diff::{self, Hunk};
+use crate::{CursorController, Row, TAB_STOP};
+use crossterm::event::KeyCode;
+use std::{cmp, iter};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+    Equal,
+    Changed,
+    /// Only on the left; the right side shows filler.
+    Deleted,
+    /// Only on the right; the left side shows filler.
+    Inserted,
+}
+
+/// One screen row of the diff: the row shown on each side, if any.
+pub struct Line {
+    pub left: Option<usize>,
+    pub right: Option<usize>,
+    pub kind: Kind,
+}
+
+/// Read-only side-by-side comparison of two files, with the rows lined up
+/// so that matching lines sit on the same screen row. Both panes share
+/// one cursor, so they always scroll together.
+pub struct DiffView {
+    pub names: (String, String),
+    pub left: Vec<Row>,
+    pub right: Vec<Row>,
+    pub lines: Vec<Line>,
+}
+
+impl DiffView {
+    pub fn new(names: (String, String), left: &str, right: &str) -> Self {
+        let old: Vec<&str> = left.lines().collect();
+        let new: Vec<&str> = right.lines().collect();
+        let end = Hunk {
+            old_start: old.len(),
+            old_len: 0,
+            new_start: new.len(),
+            new_len: 0,
+        };
+        let mut lines = Vec::new();
+        let (mut old_index, mut new_index) = (0, 0);
+        for hunk in diff::diff_lines(&old, &new).into_iter().chain(iter::once(end)) {
+            while old_index < hunk.old_start {
+                lines.push(Line {
+                    left: Some(old_index),
+                    right: Some(new_index),
+                    kind: Kind::Equal,
+                });
+                old_index += 1;
+                new_index += 1;
+            }
+            for i in 0..cmp::max(hunk.old_len, hunk.new_len) {
+                let left = (i < hunk.old_len).then(|| hunk.old_start + i);
+                let right = (i < hunk.new_len).then(|| hunk.new_start + i);
+                let kind = match (left, right) {
+                    (Some(_), Some(_)) => Kind::Changed,
+                    (Some(_), None) => Kind::Deleted,
+                    _ => Kind::Inserted,
+                };
+                lines.push(Line { left, right, kind });
+            }
+            old_index = hunk.old_start + hunk.old_len;
+            new_index = hunk.new_start + hunk.new_len;
+        }
+
+        let rows = |lines: Vec<&str>| lines.into_iter().map(|line| Row::new(line.into(), TAB_STOP)).collect();
+        Self {
+            names,
+            left: rows(old),
+            right: rows(new),
+            lines,
+        }
+    }
+
+    pub fn number_of_rows(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Screen rows where a run of differences starts.
+    fn hunk_starts(&self) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        (0..self.lines.len()).filter(move |&at| {
+            self.lines[at].kind != Kind::Equal && (at == 0 || self.lines[at - 1].kind == Kind::Equal)
+        })
+    }
+
+    pub fn number_of_hunks(&self) -> usize {
+        self.hunk_starts().count()
+    }
+
+    pub fn next_hunk(&self, at: usize) -> Option<usize> {
+        self.hunk_starts().find(|&start| start > at)
+    }
+
+    pub fn previous_hunk(&self, at: usize) -> Option<usize> {
+        self.hunk_starts().rev().find(|&start| start < at)
+    }
+
+    /// Up and Down move through the aligned rows; Left, Right and Home
+    /// scroll both panes sideways. End does nothing, since the panes'
+    /// rows have no common end.
+    pub fn move_cursor(&self, cursor: &mut CursorController, direction: KeyCode) {
+        match direction {
+            KeyCode::Up => cursor.cursor_y = cursor.cursor_y.saturating_sub(1),
+            KeyCode::Down if cursor.cursor_y + 1 < self.number_of_rows() => cursor.cursor_y += 1,
+            KeyCode::Left => cursor.column_offset = cursor.column_offset.saturating_sub(1),
+            KeyCode::Right => cursor.column_offset += 1,
+            KeyCode::Home => cursor.column_offset = 0,
+            KeyCode::End => {}
+            _ => {}
+        }
+    }
+}
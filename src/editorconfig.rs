@@ -0,0 +1,364 @@
+// Minimal .editorconfig support: https://spec.editorconfig.org
+
+use std::fs;
+use std::path::Path;
+
+const PROPERTIES: [&str; 6] = [
+    "indent_style",
+    "indent_size",
+    "tab_width",
+    "end_of_line",
+    "insert_final_newline",
+    "trim_trailing_whitespace",
+];
+
+pub struct Property {
+    pub name: String,
+    pub value: String,
+    /// Where the value came from: an .editorconfig path or a modeline.
+    pub source: String,
+}
+
+/// Properties that apply to `path`, gathered from every .editorconfig
+/// between its directory and the nearest one marked `root = true`. Closer
+/// files, and later sections within a file, win.
+pub fn properties(path: &Path) -> Vec<Property> {
+    let path = match fs::canonicalize(path).or_else(|_| std::env::current_dir().map(|dir| dir.join(path))) {
+        Ok(path) => path,
+        Err(_) => return Vec::new(),
+    };
+    let target = path.to_string_lossy().replace('\\', "/");
+    let mut files = Vec::new();
+    for directory in path.ancestors().skip(1) {
+        let config = directory.join(".editorconfig");
+        if let Ok(contents) = fs::read_to_string(&config) {
+            let root = is_root(&contents);
+            files.push((directory.to_string_lossy().replace('\\', "/"), config, contents));
+            if root {
+                break;
+            }
+        }
+    }
+
+    let mut properties: Vec<Property> = Vec::new();
+    for (directory, config, contents) in files.iter().rev() {
+        for (name, value) in matching_pairs(contents, directory, &target) {
+            properties.retain(|property| property.name != name);
+            if value != "unset" {
+                properties.push(Property {
+                    name,
+                    value,
+                    source: config.display().to_string(),
+                });
+            }
+        }
+    }
+    properties.sort_by_key(|property| PROPERTIES.iter().position(|&name| name == property.name));
+    properties
+}
+
+/// `tab_width`, which defaults to a numeric `indent_size`.
+pub fn tab_width(properties: &[Property]) -> Option<usize> {
+    let value = |name| {
+        properties
+            .iter()
+            .find(|property| property.name == name)
+            .and_then(|property| property.value.parse().ok())
+            .filter(|&width| width > 0)
+    };
+    value("tab_width").or_else(|| value("indent_size"))
+}
+
+fn pair(line: &str) -> Option<(String, String)> {
+    let (name, value) = line.split_once('=')?;
+    let name = name.trim().to_lowercase();
+    let value = value.trim();
+    if PROPERTIES.contains(&name.as_str()) || name == "root" {
+        Some((name, value.to_lowercase()))
+    } else {
+        None
+    }
+}
+
+fn is_comment(line: &str) -> bool {
+    line.is_empty() || line.starts_with('#') || line.starts_with(';')
+}
+
+fn is_root(contents: &str) -> bool {
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            break;
+        }
+        if !is_comment(line) && pair(line) == Some((String::from("root"), String::from("true"))) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Known properties set by the sections of one file whose glob matches
+/// `target`, in file order.
+fn matching_pairs(contents: &str, directory: &str, target: &str) -> Vec<(String, String)> {
+    let mut found = Vec::new();
+    let mut in_section = false;
+    for line in contents.lines().map(str::trim) {
+        if is_comment(line) {
+            continue;
+        }
+        if let Some(glob) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            in_section = section_matches(glob, directory, target);
+        } else if in_section {
+            found.extend(pair(line).filter(|(name, _)| name != "root"));
+        }
+    }
+    found
+}
+
+/// A glob without a slash matches the file name in any directory below the
+/// .editorconfig; one with a slash is anchored at its directory.
+fn section_matches(glob: &str, directory: &str, target: &str) -> bool {
+    let glob = if glob.contains('/') {
+        format!("{}/{}", directory.trim_end_matches('/'), glob.trim_start_matches('/'))
+    } else {
+        format!("{}/**/{}", directory.trim_end_matches('/'), glob)
+    };
+    let glob: Vec<char> = glob.chars().collect();
+    let target: Vec<char> = target.chars().collect();
+    glob_matches(&glob, &target)
+}
+
+/// `*` and `?` stop at `/`, `**` doesn't; also `[abc]`, `[!abc]`,
+/// `{a,b}`, `{1..10}` and `\` escapes.
+fn glob_matches(glob: &[char], text: &[char]) -> bool {
+    let (&first, rest) = match glob.split_first() {
+        Some(split) => split,
+        None => return text.is_empty(),
+    };
+    match first {
+        '*' if rest.first() == Some(&'*') => {
+            let mut rest = &rest[1..];
+            // "a/**/b" should also match "a/b"
+            if text.first() != Some(&'/') && rest.first() == Some(&'/') && glob_matches(&rest[1..], text) {
+                return true;
+            }
+            while rest.first() == Some(&'*') {
+                rest = &rest[1..];
+            }
+            (0..=text.len()).any(|i| glob_matches(rest, &text[i..]))
+        }
+        '*' => {
+            let limit = text.iter().position(|&ch| ch == '/').unwrap_or(text.len());
+            (0..=limit).any(|i| glob_matches(rest, &text[i..]))
+        }
+        '?' => matches!(text.first(), Some(&ch) if ch != '/') && glob_matches(rest, &text[1..]),
+        '[' => match class(rest) {
+            Some((negated, members, rest)) => match text.first() {
+                Some(&ch) if ch != '/' && in_class(&members, ch) != negated => glob_matches(rest, &text[1..]),
+                _ => false,
+            },
+            None => text.first() == Some(&'[') && glob_matches(rest, &text[1..]),
+        },
+        '{' => match braces(rest) {
+            Some((alternatives, rest)) => alternatives.iter().any(|alternative| {
+                let mut glob = alternative.clone();
+                glob.extend_from_slice(rest);
+                glob_matches(&glob, text)
+            }),
+            None => match range(rest) {
+                Some((low, high, rest)) => number_prefixes(text)
+                    .into_iter()
+                    .any(|(number, len)| (low..=high).contains(&number) && glob_matches(rest, &text[len..])),
+                None => text.first() == Some(&'{') && glob_matches(rest, &text[1..]),
+            },
+        },
+        '\\' if !rest.is_empty() => text.first() == Some(&rest[0]) && glob_matches(&rest[1..], &text[1..]),
+        _ => text.first() == Some(&first) && glob_matches(rest, &text[1..]),
+    }
+}
+
+/// Splits `[!a-z]`-style class contents (after the `[`) from the rest.
+fn class(glob: &[char]) -> Option<(bool, Vec<char>, &[char])> {
+    let (negated, glob) = match glob.first() {
+        Some('!') => (true, &glob[1..]),
+        _ => (false, glob),
+    };
+    let end = glob.iter().position(|&ch| ch == ']' || ch == '/')?;
+    if glob[end] == '/' {
+        return None;
+    }
+    Some((negated, glob[..end].to_vec(), &glob[end + 1..]))
+}
+
+fn in_class(members: &[char], ch: char) -> bool {
+    let mut i = 0;
+    while i < members.len() {
+        if i + 2 < members.len() && members[i + 1] == '-' {
+            if (members[i]..=members[i + 2]).contains(&ch) {
+                return true;
+            }
+            i += 3;
+        } else {
+            if members[i] == ch {
+                return true;
+            }
+            i += 1;
+        }
+    }
+    false
+}
+
+/// Splits `{a,b{c,d}}`-style alternatives (after the `{`) from the rest.
+/// Braces without a top-level comma aren't alternatives.
+fn braces(glob: &[char]) -> Option<(Vec<Vec<char>>, &[char])> {
+    let mut alternatives = vec![Vec::new()];
+    let mut depth = 0;
+    let mut escaped = false;
+    for (i, &ch) in glob.iter().enumerate() {
+        if escaped {
+            escaped = false;
+        } else if ch == '\\' {
+            escaped = true;
+        } else if ch == '{' {
+            depth += 1;
+        } else if ch == '}' && depth == 0 {
+            return (alternatives.len() > 1).then(|| (alternatives, &glob[i + 1..]));
+        } else if ch == '}' {
+            depth -= 1;
+        } else if ch == ',' && depth == 0 {
+            alternatives.push(Vec::new());
+            continue;
+        }
+        alternatives.last_mut()?.push(ch);
+    }
+    None
+}
+
+/// Parses `num1..num2}` (after the `{`).
+fn range(glob: &[char]) -> Option<(i64, i64, &[char])> {
+    let end = glob.iter().position(|&ch| ch == '}')?;
+    let body: String = glob[..end].iter().collect();
+    let (low, high) = body.split_once("..")?;
+    let (low, high): (i64, i64) = (low.parse().ok()?, high.parse().ok()?);
+    Some((low.min(high), low.max(high), &glob[end + 1..]))
+}
+
+/// Every integer `text` starts with, along with how many chars it takes.
+fn number_prefixes(text: &[char]) -> Vec<(i64, usize)> {
+    let sign = usize::from(text.first() == Some(&'-'));
+    let digits = text[sign..].iter().take_while(|ch| ch.is_ascii_digit()).count();
+    (1..=digits)
+        .filter_map(|len| {
+            let number: String = text[..sign + len].iter().collect();
+            number.parse().ok().map(|number| (number, sign + len))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn matches(glob: &str, path: &str) -> bool {
+        section_matches(glob, "/project", &format!("/project/{}", path))
+    }
+
+    /// A fresh directory tree under the temp directory, with `files`
+    /// written into it.
+    fn tree(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("rte-editorconfig-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for (path, contents) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        root
+    }
+
+    fn value(properties: &[Property], name: &str) -> Option<String> {
+        properties.iter().find(|property| property.name == name).map(|property| property.value.clone())
+    }
+
+    #[test]
+    fn alternatives() {
+        assert!(matches("*.{rs,toml}", "src/main.rs"));
+        assert!(matches("*.{rs,toml}", "Cargo.toml"));
+        assert!(!matches("*.{rs,toml}", "README.md"));
+        assert!(matches("{a,b{c,d}}.txt", "bd.txt"));
+    }
+
+    #[test]
+    fn stars() {
+        // no slash: the name, in any directory
+        assert!(matches("*.rs", "a/b/c.rs"));
+        // with a slash: anchored, and * stops at /
+        assert!(matches("src/*.rs", "src/main.rs"));
+        assert!(!matches("src/*.rs", "src/deep/main.rs"));
+        assert!(matches("src/**", "src/deep/main.rs"));
+        assert!(!matches("src/**", "lib/main.rs"));
+    }
+
+    #[test]
+    fn double_star_between_directories() {
+        assert!(matches("a/**/b", "a/b"));
+        assert!(matches("a/**/b", "a/x/b"));
+        assert!(matches("a/**/b", "a/x/y/b"));
+        assert!(!matches("a/**/b", "a/xb"));
+    }
+
+    #[test]
+    fn number_ranges() {
+        assert!(matches("file{1..3}.txt", "file1.txt"));
+        assert!(matches("file{1..3}.txt", "file3.txt"));
+        assert!(!matches("file{1..3}.txt", "file4.txt"));
+        assert!(!matches("file{1..3}.txt", "file0.txt"));
+        assert!(matches("v{-2..12}", "v10"));
+    }
+
+    #[test]
+    fn character_classes() {
+        assert!(matches("[!x]y", "ay"));
+        assert!(!matches("[!x]y", "xy"));
+        assert!(matches("[a-c].md", "b.md"));
+        assert!(!matches("[a-c].md", "d.md"));
+        assert!(matches("?.md", "d.md"));
+        assert!(!matches("a?b", "a/b"));
+    }
+
+    #[test]
+    fn root_stops_the_search_upward() {
+        let root = tree(
+            "root",
+            &[
+                (".editorconfig", "[*]\ntab_width = 3\nindent_style = tab\n"),
+                ("inner/.editorconfig", "root = true\n[*.rs]\nindent_size = 4\n"),
+                ("inner/main.rs", ""),
+                ("outer.rs", ""),
+            ],
+        );
+        let inner = properties(&root.join("inner/main.rs"));
+        assert_eq!(value(&inner, "indent_size").as_deref(), Some("4"));
+        assert_eq!(value(&inner, "tab_width"), None);
+        let outer = properties(&root.join("outer.rs"));
+        assert_eq!(value(&outer, "tab_width").as_deref(), Some("3"));
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn closer_files_and_unset_win() {
+        let root = tree(
+            "unset",
+            &[
+                (".editorconfig", "root = true\n[*]\nindent_style = space\nindent_size = 2\n"),
+                ("sub/.editorconfig", "[*.mk]\nindent_style = tab\nindent_size = unset\n"),
+                ("sub/rules.mk", ""),
+            ],
+        );
+        let found = properties(&root.join("sub/rules.mk"));
+        assert_eq!(value(&found, "indent_style").as_deref(), Some("tab"));
+        assert_eq!(value(&found, "indent_size"), None);
+        assert_eq!(tab_width(&found), None);
+        let _ = fs::remove_dir_all(root);
+    }
+}
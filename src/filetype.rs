@@ -0,0 +1,195 @@
+use std::path::Path;
+
+const FILE_NAMES: [(&str, &str); 12] = [
+    ("Makefile", "make"),
+    ("GNUmakefile", "make"),
+    ("makefile", "make"),
+    ("Dockerfile", "dockerfile"),
+    ("Containerfile", "dockerfile"),
+    ("Cargo.lock", "toml"),
+    ("CMakeLists.txt", "cmake"),
+    ("Gemfile", "ruby"),
+    ("Rakefile", "ruby"),
+    ("PKGBUILD", "sh"),
+    (".bashrc", "sh"),
+    (".gitconfig", "gitconfig"),
+];
+
+/// (prefix, suffix, file type), standing in for `prefix*suffix` globs.
+const PATTERNS: [(&str, &str, &str); 5] = [
+    ("Dockerfile.", "", "dockerfile"),
+    ("Makefile.", "", "make"),
+    (".env", "", "sh"),
+    ("", ".d.ts", "typescript"),
+    ("requirements", ".txt", "requirements"),
+];
+
+const EXTENSIONS: [(&str, &str); 25] = [
+    ("rs", "rust"),
+    ("toml", "toml"),
+    ("c", "c"),
+    ("h", "c"),
+    ("cc", "cpp"),
+    ("cpp", "cpp"),
+    ("hpp", "cpp"),
+    ("py", "python"),
+    ("rb", "ruby"),
+    ("js", "javascript"),
+    ("ts", "typescript"),
+    ("go", "go"),
+    ("java", "java"),
+    ("sh", "sh"),
+    ("bash", "sh"),
+    ("md", "markdown"),
+    ("txt", "text"),
+    ("json", "json"),
+    ("yaml", "yaml"),
+    ("yml", "yaml"),
+    ("html", "html"),
+    ("css", "css"),
+    ("lua", "lua"),
+    ("pl", "perl"),
+    ("mk", "make"),
+];
+
+const INTERPRETERS: [(&str, &str); 12] = [
+    ("sh", "sh"),
+    ("bash", "sh"),
+    ("dash", "sh"),
+    ("zsh", "sh"),
+    ("python", "python"),
+    ("ruby", "ruby"),
+    ("node", "javascript"),
+    ("perl", "perl"),
+    ("lua", "lua"),
+    ("make", "make"),
+    ("deno", "typescript"),
+    ("rust-script", "rust"),
+];
+
+/// The file type and what it was decided from: an exact file name, a
+/// name pattern, the extension, or the first line's `#!` interpreter.
+pub fn detect(path: &Path, first_line: Option<&str>) -> Option<(&'static str, &'static str)> {
+    let name = path.file_name()?.to_str()?;
+    if let Some(&(_, file_type)) = FILE_NAMES.iter().find(|&&(file_name, _)| file_name == name) {
+        return Some((file_type, "file name"));
+    }
+    if let Some(&(_, _, file_type)) = PATTERNS.iter().find(|&&(prefix, suffix, _)| {
+        name.len() > prefix.len() + suffix.len() && name.starts_with(prefix) && name.ends_with(suffix)
+    }) {
+        return Some((file_type, "file name pattern"));
+    }
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    if let Some(&(_, file_type)) = EXTENSIONS.iter().find(|&&(known, _)| Some(known) == extension) {
+        return Some((file_type, "extension"));
+    }
+    shebang(first_line?).map(|file_type| (file_type, "shebang"))
+}
+
+/// `#!/bin/sh`, `#!/usr/bin/env python3`, `#!/usr/bin/env -S deno run`.
+fn shebang(line: &str) -> Option<&'static str> {
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = words.find(|word| !word.starts_with('-') && !word.contains('='))?;
+    }
+    // python3, python3.11, ruby2.7
+    let program = program.trim_end_matches(|ch: char| ch.is_ascii_digit() || ch == '.');
+    INTERPRETERS
+        .iter()
+        .find(|&&(interpreter, _)| interpreter == program)
+        .map(|&(_, file_type)| file_type)
+}
+
+/// (file type, qualifiers that may come first, keywords that start a
+/// declaration), standing in for per-type outline regexes.
+const OUTLINES: [(&str, &[&str], &[&str]); 9] = [
+    (
+        "rust",
+        &["pub", "pub(crate)", "pub(super)", "async", "unsafe", "const", "extern", "\"C\"", "default"],
+        &["fn", "struct", "enum", "union", "trait", "impl", "mod", "type", "macro_rules!"],
+    ),
+    ("python", &["async"], &["def", "class"]),
+    ("ruby", &[], &["def", "class", "module"]),
+    ("go", &[], &["func", "type"]),
+    ("javascript", &["export", "default", "async"], &["function", "class"]),
+    ("typescript", &["export", "default", "async", "declare", "abstract"], &["function", "class", "interface", "enum", "type"]),
+    ("lua", &["local"], &["function"]),
+    ("perl", &[], &["sub", "package"]),
+    ("sh", &[], &["function"]),
+];
+
+/// Rows worth listing in an outline of a `file_type` file, with the text
+/// to show for each: declarations, or headings in markdown.
+pub fn outline<'a>(file_type: &str, lines: impl Iterator<Item = &'a str>) -> Vec<(usize, String)> {
+    let rules = OUTLINES.iter().find(|&&(known, _, _)| known == file_type);
+    let found = |line: &str| match (file_type, rules) {
+        ("markdown", _) => heading(line),
+        (_, Some(&(_, qualifiers, keywords))) => declaration(line, qualifiers, keywords),
+        (_, None) => false,
+    };
+    lines
+        .enumerate()
+        .filter(|&(_, line)| found(line))
+        .map(|(at, line)| {
+            // keep the indentation, so members show under what they're in
+            let text = line.replace('\t', "    ");
+            (at, text.trim_end_matches(|ch: char| ch == '{' || ch == ':' || ch.is_whitespace()).to_string())
+        })
+        .collect()
+}
+
+/// `# Title` through `###### Title`.
+fn heading(line: &str) -> bool {
+    let rest = line.trim_start_matches('#');
+    (1..=6).contains(&(line.len() - rest.len())) && rest.starts_with(' ')
+}
+
+/// `pub async fn name`, `impl<T> Trait for Type`: qualifiers, a keyword,
+/// then something after it.
+fn declaration(line: &str, qualifiers: &[&str], keywords: &[&str]) -> bool {
+    let mut words = line.split_whitespace().skip_while(|word| qualifiers.contains(word));
+    let word = words.next().unwrap_or_default();
+    keywords.iter().any(|&keyword| match word.strip_prefix(keyword) {
+        Some("") => words.clone().next().is_some(),
+        Some(rest) => rest.starts_with(['<', '(']),
+        None => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detected(path: &str, first_line: Option<&str>) -> Option<(&'static str, &'static str)> {
+        detect(Path::new(path), first_line)
+    }
+
+    #[test]
+    fn by_name_pattern_and_extension() {
+        assert_eq!(detected("src/Makefile", None), Some(("make", "file name")));
+        assert_eq!(detected("Dockerfile.dev", None), Some(("dockerfile", "file name pattern")));
+        assert_eq!(detected("types.d.ts", None), Some(("typescript", "file name pattern")));
+        assert_eq!(detected("main.rs", None), Some(("rust", "extension")));
+        // a pattern needs something where its * is
+        assert_eq!(detected(".env", None), None);
+    }
+
+    #[test]
+    fn name_wins_over_shebang() {
+        assert_eq!(detected("build.py", Some("#!/bin/sh")), Some(("python", "extension")));
+        assert_eq!(detected("build", Some("#!/bin/sh")), Some(("sh", "shebang")));
+        assert_eq!(detected("notes", Some("hello")), None);
+    }
+
+    #[test]
+    fn shebangs() {
+        assert_eq!(shebang("#!/bin/bash"), Some("sh"));
+        assert_eq!(shebang("#!/usr/bin/env python3"), Some("python"));
+        assert_eq!(shebang("#!/usr/bin/python3.11 -u"), Some("python"));
+        assert_eq!(shebang("#!/usr/bin/env -S deno run --allow-net"), Some("typescript"));
+        assert_eq!(shebang("#!/usr/bin/env LANG=C perl"), Some("perl"));
+        assert_eq!(shebang("#!/usr/bin/unknown"), None);
+        assert_eq!(shebang("# not a shebang"), None);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::diff::{self, Hunk};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Instant;
+
+#[derive(Clone, Copy)]
+pub enum Change {
+    Added,
+    Modified,
+    /// Rows were removed right after this row (or before row 0).
+    Deleted,
+}
+
+/// Per-row change markers against the file's blob at HEAD. The diff runs on
+/// a background thread; until it reports back, or when the file isn't
+/// tracked by git, the gutter stays inactive.
+pub struct GitGutter {
+    receiver: Option<Receiver<Vec<Hunk>>>,
+    hunks: Option<Vec<Hunk>>,
+}
+
+impl GitGutter {
+    pub fn new() -> Self {
+        Self {
+            receiver: None,
+            hunks: None,
+        }
+    }
+
+    pub fn spawn(path: &Path, rows: Vec<String>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let path = path.to_path_buf();
+        thread::spawn(move || {
+            let started = Instant::now();
+            match head_contents(&path) {
+                Some(head) => {
+                    let old: Vec<&str> = head.lines().collect();
+                    let new: Vec<&str> = rows.iter().map(String::as_str).collect();
+                    let hunks = diff::diff_lines(&old, &new);
+                    log::debug!("diffed {} against HEAD in {:?}: {} hunks", path.display(), started.elapsed(), hunks.len());
+                    let _ = sender.send(hunks);
+                }
+                None => log::debug!("{} has no blob at HEAD", path.display()),
+            }
+        });
+        Self {
+            receiver: Some(receiver),
+            hunks: None,
+        }
+    }
+
+    /// Picks up a finished diff. Returns true when the gutter just became
+    /// active.
+    pub fn poll(&mut self) -> bool {
+        let result = match &self.receiver {
+            Some(receiver) => receiver.try_recv(),
+            None => return false,
+        };
+        match result {
+            Ok(hunks) => {
+                self.hunks = Some(hunks);
+                self.receiver = None;
+                true
+            }
+            Err(TryRecvError::Disconnected) => {
+                self.receiver = None;
+                false
+            }
+            Err(TryRecvError::Empty) => false,
+        }
+    }
+
+    /// Whether the diff is still running.
+    pub fn is_pending(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.hunks.is_some()
+    }
+
+    pub fn change(&self, at: usize) -> Option<Change> {
+        self.hunks.as_ref()?.iter().find_map(|hunk| {
+            if hunk.new_len == 0 {
+                (Self::marker_row(hunk) == at).then_some(Change::Deleted)
+            } else if (hunk.new_start..hunk.new_start + hunk.new_len).contains(&at) {
+                if at - hunk.new_start < hunk.old_len {
+                    Some(Change::Modified)
+                } else {
+                    Some(Change::Added)
+                }
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn next_change(&self, at: usize) -> Option<usize> {
+        self.hunks
+            .as_ref()?
+            .iter()
+            .map(Self::marker_row)
+            .find(|&row| row > at)
+    }
+
+    pub fn previous_change(&self, at: usize) -> Option<usize> {
+        self.hunks
+            .as_ref()?
+            .iter()
+            .rev()
+            .map(Self::marker_row)
+            .find(|&row| row < at)
+    }
+
+    /// The row each hunk is marked on, with its kind.
+    pub fn markers(&self) -> Vec<(usize, Change)> {
+        let hunks = match &self.hunks {
+            Some(hunks) => hunks,
+            None => return Vec::new(),
+        };
+        hunks
+            .iter()
+            .map(|hunk| {
+                let change = if hunk.new_len == 0 {
+                    Change::Deleted
+                } else if hunk.old_len == 0 {
+                    Change::Added
+                } else {
+                    Change::Modified
+                };
+                (Self::marker_row(hunk), change)
+            })
+            .collect()
+    }
+
+    fn marker_row(hunk: &Hunk) -> usize {
+        if hunk.new_len == 0 {
+            hunk.new_start.saturating_sub(1)
+        } else {
+            hunk.new_start
+        }
+    }
+}
+
+fn head_contents(path: &Path) -> Option<String> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let file_name = path.file_name()?.to_str()?;
+    let output = Command::new("git")
+        .arg("show")
+        .arg(format!("HEAD:./{}", file_name))
+        .current_dir(directory)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
@@ -0,0 +1,181 @@
+use crate::CursorController;
+use crossterm::event::KeyCode;
+use std::cmp;
+
+const BYTES_PER_ROW: usize = 16;
+const OFFSET_WIDTH: usize = 10;
+
+/// Read-only view of a file that isn't text, laid out as a classic
+/// offset / hex / ASCII dump with 16 bytes per row.
+pub struct HexView {
+    bytes: Vec<u8>,
+}
+
+impl HexView {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self { bytes }
+    }
+
+    pub fn number_of_rows(&self) -> usize {
+        self.bytes.len().div_ceil(BYTES_PER_ROW)
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn byte_offset(&self, cursor_x: usize, cursor_y: usize) -> usize {
+        cursor_y * BYTES_PER_ROW + cursor_x
+    }
+
+    /// Column and row of byte `offset`, clamped to the last byte.
+    pub fn position_of_byte(&self, offset: usize) -> (usize, usize) {
+        let offset = cmp::min(offset, self.bytes.len().saturating_sub(1));
+        (offset % BYTES_PER_ROW, offset / BYTES_PER_ROW)
+    }
+
+    pub fn row_len(&self, at: usize) -> usize {
+        if at < self.number_of_rows() {
+            self.row_bytes(at).len()
+        } else {
+            0
+        }
+    }
+
+    fn row_bytes(&self, at: usize) -> &[u8] {
+        let start = at * BYTES_PER_ROW;
+        &self.bytes[start..cmp::min(start + BYTES_PER_ROW, self.bytes.len())]
+    }
+
+    /// Screen column of the hex digits for byte `cursor_x` of a row.
+    pub fn render_x(cursor_x: usize) -> usize {
+        OFFSET_WIDTH + cursor_x * 3 + cursor_x / 8
+    }
+
+    pub fn render_row(&self, at: usize) -> String {
+        let bytes = self.row_bytes(at);
+        let mut row = format!("{:08x}  ", at * BYTES_PER_ROW);
+        for i in 0..BYTES_PER_ROW {
+            if i == 8 {
+                row.push(' ');
+            }
+            match bytes.get(i) {
+                Some(byte) => row.push_str(&format!("{:02x} ", byte)),
+                None => row.push_str("   "),
+            }
+        }
+        row.push_str(" |");
+        for &byte in bytes {
+            row.push(if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            });
+        }
+        row.push('|');
+        row
+    }
+
+    pub fn move_cursor(&self, cursor: &mut CursorController, direction: KeyCode) {
+        let number_of_rows = self.number_of_rows();
+        match direction {
+            KeyCode::Up => cursor.cursor_y = cursor.cursor_y.saturating_sub(1),
+            KeyCode::Down if cursor.cursor_y + 1 < number_of_rows => cursor.cursor_y += 1,
+            KeyCode::Left => {
+                if cursor.cursor_x != 0 {
+                    cursor.cursor_x -= 1;
+                } else if cursor.cursor_y > 0 {
+                    cursor.cursor_y -= 1;
+                    cursor.cursor_x = BYTES_PER_ROW - 1;
+                }
+            }
+            KeyCode::Right => {
+                if cursor.cursor_x + 1 < BYTES_PER_ROW {
+                    cursor.cursor_x += 1;
+                } else if cursor.cursor_y + 1 < number_of_rows {
+                    cursor.cursor_y += 1;
+                    cursor.cursor_x = 0;
+                }
+            }
+            KeyCode::Home => cursor.cursor_x = 0,
+            KeyCode::End => cursor.cursor_x = BYTES_PER_ROW - 1,
+            _ => {}
+        }
+
+        cursor.cursor_x = cmp::min(cursor.cursor_x, self.row_len(cursor.cursor_y).saturating_sub(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 20 bytes: a full row and a short one of 4.
+    fn view() -> HexView {
+        let mut bytes = b"Hello, hex\tview!".to_vec();
+        bytes.extend([0x00, 0x7f, 0xff, b' ']);
+        HexView::new(bytes)
+    }
+
+    #[test]
+    fn rows() {
+        let view = view();
+        assert_eq!(view.number_of_rows(), 2);
+        assert_eq!(
+            view.render_row(0),
+            "00000000  48 65 6c 6c 6f 2c 20 68  65 78 09 76 69 65 77 21  |Hello, hex.view!|"
+        );
+        // a short row keeps the ASCII column where it is
+        assert_eq!(
+            view.render_row(1),
+            "00000010  00 7f ff 20                                       |... |"
+        );
+        assert_eq!(view.render_row(0).find('|'), view.render_row(1).find('|'));
+        assert_eq!(HexView::new(Vec::new()).number_of_rows(), 0);
+    }
+
+    #[test]
+    fn hex_columns() {
+        let row = view().render_row(0);
+        for (cursor_x, byte) in [(0, "48"), (7, "68"), (8, "65"), (15, "21")] {
+            let at = HexView::render_x(cursor_x);
+            assert_eq!(&row[at..at + 2], byte, "byte {}", cursor_x);
+        }
+    }
+
+    #[test]
+    fn offsets() {
+        let view = view();
+        assert_eq!(view.byte_offset(3, 1), 19);
+        assert_eq!(view.position_of_byte(0), (0, 0));
+        assert_eq!(view.position_of_byte(17), (1, 1));
+        // past the end is the last byte
+        assert_eq!(view.position_of_byte(1000), (3, 1));
+        assert_eq!(HexView::new(Vec::new()).position_of_byte(5), (0, 0));
+        assert_eq!((view.row_len(0), view.row_len(1), view.row_len(2)), (16, 4, 0));
+    }
+
+    #[test]
+    fn moves_stay_on_the_bytes() {
+        let view = view();
+        let mut cursor = CursorController::new((80, 24));
+        let mut move_to = |direction| {
+            view.move_cursor(&mut cursor, direction);
+            (cursor.cursor_x, cursor.cursor_y)
+        };
+        assert_eq!(move_to(KeyCode::End), (15, 0));
+        // down onto the short row lands on its last byte
+        assert_eq!(move_to(KeyCode::Down), (3, 1));
+        assert_eq!(move_to(KeyCode::Down), (3, 1));
+        assert_eq!(move_to(KeyCode::End), (3, 1));
+        assert_eq!(move_to(KeyCode::Right), (3, 1));
+        assert_eq!(move_to(KeyCode::Home), (0, 1));
+        // Left and Right wrap between rows
+        assert_eq!(move_to(KeyCode::Left), (15, 0));
+        assert_eq!(move_to(KeyCode::Right), (0, 1));
+        assert_eq!(move_to(KeyCode::Up), (0, 0));
+        assert_eq!(move_to(KeyCode::Up), (0, 0));
+        assert_eq!(move_to(KeyCode::Left), (0, 0));
+        assert_eq!(move_to(KeyCode::PageDown), (0, 0));
+    }
+}
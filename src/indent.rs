@@ -0,0 +1,88 @@
+/// How many lines from the top of the file are looked at.
+pub const SAMPLE_LINES: usize = 500;
+
+pub enum Indentation {
+    Tabs,
+    Spaces(usize),
+    Mixed,
+}
+
+/// Guesses the indentation style from how lines are indented. The width
+/// of space indentation is the most common step in indentation between
+/// consecutive indented lines, so alignment and deep nesting don't skew it.
+pub fn detect<'a>(lines: impl Iterator<Item = &'a str>) -> Option<Indentation> {
+    let (mut tab_lines, mut space_lines) = (0, 0);
+    let mut steps = [0usize; 9];
+    let mut previous = 0;
+    for line in lines.take(SAMPLE_LINES) {
+        let content = line.trim_start_matches([' ', '\t']);
+        if content.is_empty() {
+            continue;
+        }
+        let whitespace = &line[..line.len() - content.len()];
+        if whitespace.starts_with('\t') {
+            tab_lines += 1;
+            previous = 0;
+            continue;
+        }
+        let spaces = whitespace.len();
+        if spaces > previous && spaces - previous < steps.len() {
+            steps[spaces - previous] += 1;
+        }
+        if spaces > 0 {
+            space_lines += 1;
+        }
+        previous = spaces;
+    }
+
+    if tab_lines == 0 && space_lines == 0 {
+        None
+    } else if tab_lines > 2 * space_lines {
+        Some(Indentation::Tabs)
+    } else if space_lines > 2 * tab_lines {
+        let width = (2..steps.len()).max_by_key(|&width| (steps[width], usize::MAX - width))?;
+        (steps[width] > 0).then_some(Indentation::Spaces(width))
+    } else {
+        Some(Indentation::Mixed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guess(text: &str) -> Option<Indentation> {
+        detect(text.lines())
+    }
+
+    #[test]
+    fn tabs() {
+        assert!(matches!(guess("fn a() {\n\tb();\n\tif c {\n\t\td();\n\t}\n}"), Some(Indentation::Tabs)));
+    }
+
+    #[test]
+    fn spaces_by_the_most_common_step() {
+        let text = "a:\n    b:\n        c\n    d\n    e:\n        f\n";
+        assert!(matches!(guess(text), Some(Indentation::Spaces(4))));
+        let text = "a\n  b\n    c\n  d\n    e\n";
+        assert!(matches!(guess(text), Some(Indentation::Spaces(2))));
+    }
+
+    #[test]
+    fn alignment_does_not_skew_the_width() {
+        // continuation lines lined up under an argument take odd steps
+        let text = "fn a() {\n    call(one,\n         two);\n    b();\n    if c {\n        d();\n    }\n}\n";
+        assert!(matches!(guess(text), Some(Indentation::Spaces(4))));
+    }
+
+    #[test]
+    fn mixed() {
+        assert!(matches!(guess("a\n\tb\n    c\n\td\n    e\n"), Some(Indentation::Mixed)));
+    }
+
+    #[test]
+    fn nothing_indented() {
+        assert!(guess("a\nb\n\n   \nc\n").is_none());
+        assert!(guess("").is_none());
+    }
+}
@@ -0,0 +1,212 @@
+use crate::diff::{self, Hunk};
+
+/// Held on to so Ctrl-O can go back a long way, but not forever.
+const LIMIT: usize = 100;
+
+#[derive(Clone, PartialEq)]
+pub struct Position {
+    pub file: String,
+    pub cursor_x: usize,
+    pub cursor_y: usize,
+}
+
+/// Where long jumps (go to, tags, the outline, locations, bookmarks,
+/// conflicts) started, walked with Ctrl-O and Ctrl-I.
+pub struct JumpList {
+    entries: Vec<Position>,
+    /// The entry Ctrl-O and Ctrl-I last went to; `entries.len()` while
+    /// not walking the list.
+    current: usize,
+}
+
+impl JumpList {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            current: 0,
+        }
+    }
+
+    /// Records `from` before a jump. Entries newer than the one walked back
+    /// to are dropped, as is `from` if it's the newest entry already.
+    pub fn push(&mut self, from: Position) {
+        self.entries.truncate(self.current);
+        if self.entries.last() != Some(&from) {
+            self.entries.push(from);
+        }
+        if self.entries.len() > LIMIT {
+            self.entries.remove(0);
+        }
+        self.current = self.entries.len();
+    }
+
+    /// The entry before the current one. Leaving the newest position
+    /// records `here` first, so Ctrl-I can come back to it.
+    pub fn back(&mut self, here: Position) -> Option<&Position> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        if self.current == self.entries.len() {
+            self.push(here);
+            // `here` was the only entry: nowhere to go, and nothing to walk
+            if self.entries.len() == 1 {
+                return None;
+            }
+            self.current = self.entries.len() - 1;
+        }
+        self.current = self.current.checked_sub(1)?;
+        self.entries.get(self.current)
+    }
+
+    pub fn forward(&mut self) -> Option<&Position> {
+        if self.current + 1 >= self.entries.len() {
+            return None;
+        }
+        self.current += 1;
+        self.entries.get(self.current)
+    }
+
+    /// Which entry was last gone to, from 1, and how many there are.
+    pub fn position(&self) -> (usize, usize) {
+        (self.current + 1, self.entries.len())
+    }
+
+    /// Drops the entry last gone to, for a file that's gone.
+    pub fn remove_current(&mut self) {
+        if self.current < self.entries.len() {
+            self.entries.remove(self.current);
+        }
+    }
+
+    /// Follows `file`'s entries through the hunks of a reload. Entries on
+    /// deleted lines are dropped.
+    pub fn follow(&mut self, file: &str, hunks: &[Hunk]) {
+        let current = self.current;
+        let mut kept = 0;
+        let mut at = 0;
+        self.entries.retain_mut(|entry| {
+            let keep = match diff::map_row(hunks, entry.cursor_y) {
+                _ if entry.file != file => true,
+                Some(row) => {
+                    entry.cursor_y = row;
+                    true
+                }
+                None => false,
+            };
+            if keep && at < current {
+                kept += 1;
+            }
+            at += 1;
+            keep
+        });
+        self.current = kept;
+    }
+
+    pub fn has_file(&self, file: &str) -> bool {
+        self.entries.iter().any(|entry| entry.file == file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(file: &str, cursor_y: usize) -> Position {
+        Position {
+            file: file.to_string(),
+            cursor_x: 0,
+            cursor_y,
+        }
+    }
+
+    fn rows(jumps: &JumpList) -> Vec<usize> {
+        jumps.entries.iter().map(|entry| entry.cursor_y).collect()
+    }
+
+    #[test]
+    fn back_and_forward() {
+        let mut jumps = JumpList::new();
+        assert!(jumps.back(at("a", 0)).is_none());
+        jumps.push(at("a", 1));
+        jumps.push(at("a", 2));
+        // leaving row 3 records it, so Ctrl-I can come back
+        assert_eq!(jumps.back(at("a", 3)).map(|entry| entry.cursor_y), Some(2));
+        assert_eq!(jumps.position(), (2, 3));
+        assert_eq!(jumps.back(at("a", 2)).map(|entry| entry.cursor_y), Some(1));
+        assert!(jumps.back(at("a", 1)).is_none());
+        assert_eq!(jumps.forward().map(|entry| entry.cursor_y), Some(2));
+        assert_eq!(jumps.forward().map(|entry| entry.cursor_y), Some(3));
+        assert!(jumps.forward().is_none());
+        // a jump from partway back drops the newer entries
+        jumps.back(at("a", 3));
+        jumps.back(at("a", 2));
+        jumps.push(at("a", 1));
+        jumps.push(at("a", 7));
+        assert_eq!(rows(&jumps), [1, 7]);
+    }
+
+    #[test]
+    fn back_from_the_only_entry_keeps_it() {
+        let mut jumps = JumpList::new();
+        jumps.push(at("a", 5));
+        assert!(jumps.back(at("a", 5)).is_none());
+        assert_eq!(jumps.position(), (2, 1));
+        jumps.push(at("a", 9));
+        assert_eq!(rows(&jumps), [5, 9]);
+    }
+
+    #[test]
+    fn repeats_are_recorded_once() {
+        let mut jumps = JumpList::new();
+        jumps.push(at("a", 1));
+        jumps.push(at("a", 1));
+        jumps.push(at("b", 1));
+        jumps.push(at("a", 1));
+        assert_eq!(jumps.entries.len(), 3);
+    }
+
+    #[test]
+    fn oldest_entries_go_past_the_limit() {
+        let mut jumps = JumpList::new();
+        for row in 0..LIMIT + 20 {
+            jumps.push(at("a", row));
+        }
+        assert_eq!(jumps.entries.len(), LIMIT);
+        assert_eq!(jumps.entries[0].cursor_y, 20);
+        assert_eq!(jumps.position(), (LIMIT + 1, LIMIT));
+    }
+
+    #[test]
+    fn entries_follow_a_reload() {
+        let mut jumps = JumpList::new();
+        for row in [1, 5, 10, 20] {
+            jumps.push(at("a", row));
+        }
+        jumps.push(at("b", 5));
+        jumps.back(at("b", 6));
+        jumps.back(at("b", 5));
+        jumps.back(at("a", 20));
+        assert_eq!(jumps.position(), (3, 6));
+        // rows 4..6 deleted, two rows inserted before row 15
+        let hunks = [
+            Hunk {
+                old_start: 4,
+                old_len: 3,
+                new_start: 4,
+                new_len: 0,
+            },
+            Hunk {
+                old_start: 15,
+                old_len: 0,
+                new_start: 12,
+                new_len: 2,
+            },
+        ];
+        jumps.follow("a", &hunks);
+        assert_eq!(rows(&jumps), [1, 7, 19, 5, 6]);
+        // still on the entry for old row 10
+        assert_eq!(jumps.position(), (2, 5));
+        assert!(jumps.has_file("b"));
+        assert!(!jumps.has_file("c"));
+    }
+}
@@ -0,0 +1,295 @@
+// Every key binding, in tables that both the dispatcher and the help
+// screen read, so the help can't list a key that doesn't work.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Action {
+    Quit,
+    #[cfg(unix)]
+    Suspend,
+    Help,
+    Move(KeyCode),
+    PageUp,
+    PageDown,
+    ScrollDown,
+    ScrollUp,
+    HalfPageDown,
+    HalfPageUp,
+    GoTo,
+    NextChange,
+    PreviousChange,
+    NextConflict,
+    PreviousConflict,
+    GoToDefinition,
+    PopTag,
+    JumpBack,
+    JumpForward,
+    Outline,
+    Locations,
+    NextLocation,
+    PreviousLocation,
+    FindTodos,
+    ToggleBookmark,
+    NextBookmark,
+    PreviousBookmark,
+    ClearBookmarks,
+    Fold,
+    Unfold,
+    FoldAll,
+    UnfoldAll,
+    ToggleScrollbar,
+    DescribeCharacter,
+    ListInvisible,
+    ShowSettings,
+    ToggleSpelling,
+    SuggestSpelling,
+    AddToDictionary,
+    ToggleFrameStats,
+}
+
+/// Categories in the order the help lists them.
+pub const CATEGORIES: [&str; 5] = ["Movement", "Navigation", "View", "Information", "Editor"];
+
+impl Action {
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            #[cfg(unix)]
+            Action::Suspend => "Suspend to the shell",
+            Action::Help => "Show this help",
+            Action::Move(KeyCode::Up) => "Up a row",
+            Action::Move(KeyCode::Down) => "Down a row",
+            Action::Move(KeyCode::Left) => "Left a character",
+            Action::Move(KeyCode::Right) => "Right a character",
+            Action::Move(KeyCode::Home) => "Start of the text, then of the row",
+            Action::Move(KeyCode::End) => "End of the row",
+            Action::Move(_) => "Move",
+            Action::PageUp => "Up a screen",
+            Action::PageDown => "Down a screen",
+            Action::ScrollDown => "Scroll down a row",
+            Action::ScrollUp => "Scroll up a row",
+            Action::HalfPageDown => "Down half a screen",
+            Action::HalfPageUp => "Up half a screen",
+            Action::GoTo => "Go to a byte offset or percentage",
+            Action::NextChange => "Next git change or difference",
+            Action::PreviousChange => "Previous git change or difference",
+            Action::NextConflict => "Next merge conflict",
+            Action::PreviousConflict => "Previous merge conflict",
+            Action::GoToDefinition => "Go to the definition (tags file)",
+            Action::PopTag => "Back from a definition",
+            Action::JumpBack => "Back to where the last long jump started",
+            Action::JumpForward => "Forward again in the jump list",
+            Action::Outline => "Outline of the file",
+            Action::Locations => "Show the location list",
+            Action::NextLocation => "Next entry in the location list",
+            Action::PreviousLocation => "Previous entry in the location list",
+            Action::FindTodos => "List TODO and FIXME notes in the location list",
+            Action::ToggleBookmark => "Bookmark the row, or remove its bookmark",
+            Action::NextBookmark => "Next bookmark",
+            Action::PreviousBookmark => "Previous bookmark",
+            Action::ClearBookmarks => "Remove all bookmarks in the file",
+            Action::Fold => "Fold the block",
+            Action::Unfold => "Unfold",
+            Action::FoldAll => "Fold all blocks",
+            Action::UnfoldAll => "Unfold everything",
+            Action::ToggleScrollbar => "Show or hide the scrollbar",
+            Action::DescribeCharacter => "Describe the character under the cursor",
+            Action::ListInvisible => "List invisible characters",
+            Action::ShowSettings => "Show the settings in effect",
+            Action::ToggleSpelling => "Check spelling (markdown and text), or stop",
+            Action::SuggestSpelling => "Suggest spellings for the word under the cursor",
+            Action::AddToDictionary => "Add the word under the cursor to the personal dictionary",
+            Action::ToggleFrameStats => "Show or hide redraw times, with the totals so far",
+        }
+    }
+
+    pub fn category(self) -> &'static str {
+        match self {
+            Action::Move(_)
+            | Action::PageUp
+            | Action::PageDown
+            | Action::ScrollDown
+            | Action::ScrollUp
+            | Action::HalfPageDown
+            | Action::HalfPageUp => "Movement",
+            Action::GoTo
+            | Action::NextChange
+            | Action::PreviousChange
+            | Action::NextConflict
+            | Action::PreviousConflict
+            | Action::GoToDefinition
+            | Action::PopTag
+            | Action::JumpBack
+            | Action::JumpForward
+            | Action::Outline
+            | Action::Locations
+            | Action::NextLocation
+            | Action::PreviousLocation
+            | Action::FindTodos
+            | Action::ToggleBookmark
+            | Action::NextBookmark
+            | Action::PreviousBookmark
+            | Action::ClearBookmarks => "Navigation",
+            Action::Fold | Action::Unfold | Action::FoldAll | Action::UnfoldAll | Action::ToggleScrollbar => "View",
+            Action::DescribeCharacter
+            | Action::ListInvisible
+            | Action::ShowSettings
+            | Action::ToggleSpelling
+            | Action::SuggestSpelling
+            | Action::AddToDictionary
+            | Action::ToggleFrameStats => "Information",
+            _ => "Editor",
+        }
+    }
+}
+
+const NONE: KeyModifiers = KeyModifiers::NONE;
+const CONTROL: KeyModifiers = KeyModifiers::CONTROL;
+const SHIFT: KeyModifiers = KeyModifiers::SHIFT;
+const ALT: KeyModifiers = KeyModifiers::ALT;
+
+pub const BINDINGS: &[(KeyCode, KeyModifiers, Action)] = &[
+    (KeyCode::Char('q'), CONTROL, Action::Quit),
+    #[cfg(unix)]
+    (KeyCode::Char('z'), CONTROL, Action::Suspend),
+    (KeyCode::F(1), NONE, Action::Help),
+    (KeyCode::Char('h'), CONTROL, Action::Help),
+    (KeyCode::Up, NONE, Action::Move(KeyCode::Up)),
+    (KeyCode::Down, NONE, Action::Move(KeyCode::Down)),
+    (KeyCode::Left, NONE, Action::Move(KeyCode::Left)),
+    (KeyCode::Right, NONE, Action::Move(KeyCode::Right)),
+    (KeyCode::Home, NONE, Action::Move(KeyCode::Home)),
+    (KeyCode::End, NONE, Action::Move(KeyCode::End)),
+    (KeyCode::PageUp, NONE, Action::PageUp),
+    (KeyCode::PageDown, NONE, Action::PageDown),
+    (KeyCode::Char('e'), CONTROL, Action::ScrollDown),
+    (KeyCode::Char('y'), CONTROL, Action::ScrollUp),
+    (KeyCode::Char('d'), CONTROL, Action::HalfPageDown),
+    (KeyCode::Char('u'), CONTROL, Action::HalfPageUp),
+    (KeyCode::Char('g'), CONTROL, Action::GoTo),
+    (KeyCode::F(5), NONE, Action::NextChange),
+    (KeyCode::F(5), SHIFT, Action::PreviousChange),
+    (KeyCode::F(2), NONE, Action::NextConflict),
+    (KeyCode::F(2), SHIFT, Action::PreviousConflict),
+    (KeyCode::F(12), NONE, Action::GoToDefinition),
+    (KeyCode::Char('.'), ALT, Action::GoToDefinition),
+    (KeyCode::Char('t'), CONTROL, Action::PopTag),
+    (KeyCode::Char(','), ALT, Action::PopTag),
+    (KeyCode::Char('o'), CONTROL, Action::JumpBack),
+    (KeyCode::Left, ALT, Action::JumpBack),
+    // what terminals send for Ctrl-I
+    (KeyCode::Tab, NONE, Action::JumpForward),
+    (KeyCode::Right, ALT, Action::JumpForward),
+    (KeyCode::F(10), NONE, Action::Outline),
+    (KeyCode::Char('l'), CONTROL, Action::Locations),
+    (KeyCode::Char('n'), CONTROL, Action::NextLocation),
+    (KeyCode::Char('p'), CONTROL, Action::PreviousLocation),
+    (KeyCode::F(3), NONE, Action::FindTodos),
+    (KeyCode::Down, CONTROL, Action::NextBookmark),
+    (KeyCode::Up, CONTROL, Action::PreviousBookmark),
+    (KeyCode::F(7), NONE, Action::Fold),
+    (KeyCode::F(8), NONE, Action::Unfold),
+    (KeyCode::F(7), SHIFT, Action::FoldAll),
+    (KeyCode::F(8), SHIFT, Action::UnfoldAll),
+    (KeyCode::F(6), NONE, Action::ToggleScrollbar),
+    (KeyCode::Char('k'), CONTROL, Action::DescribeCharacter),
+    (KeyCode::F(4), NONE, Action::ListInvisible),
+    (KeyCode::F(9), NONE, Action::ShowSettings),
+];
+
+const CTRL_X: (KeyCode, KeyModifiers) = (KeyCode::Char('x'), CONTROL);
+
+/// Two-key bindings: a prefix, then a key. They're a second way to the
+/// actions on function keys that terminals often keep for themselves, and
+/// the only way to the less used ones.
+pub const CHORDS: &[((KeyCode, KeyModifiers), KeyCode, KeyModifiers, Action)] = &[
+    (CTRL_X, KeyCode::Char('c'), CONTROL, Action::Quit),
+    (CTRL_X, KeyCode::Char('h'), NONE, Action::Help),
+    (CTRL_X, KeyCode::Char('o'), NONE, Action::Outline),
+    (CTRL_X, KeyCode::Char('d'), NONE, Action::GoToDefinition),
+    (CTRL_X, KeyCode::Char('t'), NONE, Action::FindTodos),
+    (CTRL_X, KeyCode::Char('b'), NONE, Action::ToggleBookmark),
+    (CTRL_X, KeyCode::Char('B'), SHIFT, Action::ClearBookmarks),
+    (CTRL_X, KeyCode::Char('i'), NONE, Action::ListInvisible),
+    (CTRL_X, KeyCode::Char('s'), NONE, Action::ShowSettings),
+    (CTRL_X, KeyCode::Char('w'), NONE, Action::ToggleSpelling),
+    (CTRL_X, KeyCode::Char('='), NONE, Action::SuggestSpelling),
+    (CTRL_X, KeyCode::Char('a'), NONE, Action::AddToDictionary),
+    (CTRL_X, KeyCode::Char('p'), NONE, Action::ToggleFrameStats),
+];
+
+pub fn action(key_event: KeyEvent) -> Option<Action> {
+    BINDINGS
+        .iter()
+        .find(|&&(code, modifiers, _)| code == key_event.code && modifiers == key_event.modifiers)
+        .map(|&(_, _, action)| action)
+}
+
+/// Whether `key_event` starts a chord; the dispatcher waits for the
+/// second key after it.
+pub fn is_prefix(key_event: KeyEvent) -> bool {
+    CHORDS.iter().any(|&(prefix, _, _, _)| prefix == (key_event.code, key_event.modifiers))
+}
+
+pub fn chord(prefix: KeyEvent, key_event: KeyEvent) -> Option<Action> {
+    CHORDS
+        .iter()
+        .find(|&&(first, code, modifiers, _)| {
+            first == (prefix.code, prefix.modifiers) && code == key_event.code && modifiers == key_event.modifiers
+        })
+        .map(|&(_, _, _, action)| action)
+}
+
+/// `Ctrl-Q`, `Shift-F5`, `PageUp`.
+pub fn key_name(code: KeyCode, modifiers: KeyModifiers) -> String {
+    let key = match code {
+        KeyCode::Char(ch) => ch.to_ascii_uppercase().to_string(),
+        KeyCode::F(number) => format!("F{}", number),
+        other => format!("{:?}", other),
+    };
+    let mut name = String::new();
+    if modifiers.contains(KeyModifiers::CONTROL) {
+        name.push_str("Ctrl-");
+    }
+    if modifiers.contains(KeyModifiers::ALT) {
+        name.push_str("Alt-");
+    }
+    if modifiers.contains(KeyModifiers::SHIFT) {
+        name.push_str("Shift-");
+    }
+    name + &key
+}
+
+/// Every key and chord bound to `action`: `F1, Ctrl-H, Ctrl-X H`.
+pub fn key_names(action: Action) -> String {
+    let keys = BINDINGS
+        .iter()
+        .filter(|&&(_, _, bound)| bound == action)
+        .map(|&(code, modifiers, _)| key_name(code, modifiers));
+    let chords = CHORDS.iter().filter(|&&(_, _, _, bound)| bound == action).map(
+        |&((prefix, prefix_modifiers), code, modifiers, _)| {
+            format!("{} {}", key_name(prefix, prefix_modifiers), key_name(code, modifiers))
+        },
+    );
+    keys.chain(chords).collect::<Vec<_>>().join(", ")
+}
+
+/// One line per action, grouped by category: the category, every key
+/// and chord bound to the action, and what it does.
+pub fn help_lines() -> Vec<String> {
+    let bound = BINDINGS.iter().map(|&(_, _, action)| action).chain(CHORDS.iter().map(|&(_, _, _, action)| action));
+    let mut actions: Vec<Action> = Vec::new();
+    for action in bound {
+        if !actions.contains(&action) {
+            actions.push(action);
+        }
+    }
+    let mut lines = Vec::new();
+    for category in CATEGORIES {
+        for &action in actions.iter().filter(|action| action.category() == category) {
+            lines.push(format!("{:<12}{:<24}{}", category, key_names(action), action.description()));
+        }
+    }
+    lines
+}
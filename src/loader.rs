@@ -0,0 +1,278 @@
+use std::cmp;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+pub enum Loaded {
+    /// Complete lines read so far, and the number of bytes they came from.
+    Lines(Vec<String>, u64),
+    /// How the first line ends, sent before the first lines. The lines
+    /// themselves come without it.
+    LineEnding(LineEnding),
+    /// The file has NUL bytes or invalid UTF-8, so it's going to the hex
+    /// view; here are all of its bytes.
+    Binary(Vec<u8>),
+    Done,
+    Failed(io::Error),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    /// In bytes.
+    pub fn len(self) -> usize {
+        match self {
+            LineEnding::Lf => 1,
+            LineEnding::Crlf => 2,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "LF",
+            LineEnding::Crlf => "CRLF",
+        }
+    }
+}
+
+pub enum Source {
+    File(PathBuf),
+    /// Read to the end before anything is sent, since it can't be read
+    /// again if it turns out to be binary.
+    Stdin,
+}
+
+/// Reads a file on a background thread, streaming it back line by line so
+/// the top of the file can be shown before the rest has arrived.
+pub struct Loader {
+    receiver: Receiver<Loaded>,
+    cancelled: Arc<AtomicBool>,
+    total: u64,
+    read: u64,
+    started: Instant,
+}
+
+impl Loader {
+    pub fn spawn(source: Source) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let total = match &source {
+            Source::File(path) => fs::metadata(path).map_or(0, |metadata| metadata.len()),
+            Source::Stdin => 0,
+        };
+        let flag = Arc::clone(&cancelled);
+        thread::spawn(move || {
+            let loaded = match source {
+                Source::File(path) => File::open(&path).and_then(|file| load(file, || fs::read(&path), &sender, &flag)),
+                Source::Stdin => read_stdin().and_then(|bytes| load(&bytes[..], || Ok(bytes.clone()), &sender, &flag)),
+            };
+            let message = match loaded {
+                Ok(message) => message,
+                Err(error) => Loaded::Failed(error),
+            };
+            let _ = sender.send(message);
+        });
+        Self {
+            receiver,
+            cancelled,
+            total,
+            read: 0,
+            started: Instant::now(),
+        }
+    }
+
+    /// Everything the loader has produced since the last call.
+    pub fn poll(&mut self) -> Vec<Loaded> {
+        let messages: Vec<Loaded> = self.receiver.try_iter().collect();
+        for message in &messages {
+            if let Loaded::Lines(_, bytes) = message {
+                self.read += bytes;
+            }
+        }
+        messages
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn percent(&self) -> u64 {
+        let percent = (self.read * 100).checked_div(self.total).unwrap_or(0);
+        cmp::min(percent, 100)
+    }
+}
+
+impl Drop for Loader {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed)
+    }
+}
+
+fn read_stdin() -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    io::stdin().lock().read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// `reread` gets all of the bytes again for the hex view.
+fn load<R: Read, F: Fn() -> io::Result<Vec<u8>>>(
+    mut file: R,
+    reread: F,
+    sender: &Sender<Loaded>,
+    cancelled: &AtomicBool,
+) -> io::Result<Loaded> {
+    let mut chunk = vec![0; CHUNK_SIZE];
+    let mut pending = Vec::new();
+    let mut line_ending = None;
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return Ok(Loaded::Done);
+        }
+        let read = match file.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        };
+        if chunk[..read].contains(&0) {
+            return Ok(Loaded::Binary(reread()?));
+        }
+        pending.extend_from_slice(&chunk[..read]);
+        let complete = match pending.iter().rposition(|&byte| byte == b'\n') {
+            Some(newline) => newline + 1,
+            None => continue,
+        };
+        let rest = pending.split_off(complete);
+        let text = match String::from_utf8(pending) {
+            Ok(text) => text,
+            Err(_) => return Ok(Loaded::Binary(reread()?)),
+        };
+        pending = rest;
+        if line_ending.is_none() {
+            let crlf = text.find('\n').is_some_and(|newline| text[..newline].ends_with('\r'));
+            let ending = if crlf { LineEnding::Crlf } else { LineEnding::Lf };
+            line_ending = Some(ending);
+            let _ = sender.send(Loaded::LineEnding(ending));
+        }
+        let lines = text.lines().map(String::from).collect();
+        if sender.send(Loaded::Lines(lines, text.len() as u64)).is_err() {
+            return Ok(Loaded::Done);
+        }
+    }
+    if !pending.is_empty() {
+        let text = match String::from_utf8(pending) {
+            Ok(text) => text,
+            Err(_) => return Ok(Loaded::Binary(reread()?)),
+        };
+        let _ = sender.send(Loaded::Lines(text.lines().map(String::from).collect(), text.len() as u64));
+    }
+    Ok(Loaded::Done)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hands out at most `step` bytes per read, so lines straddle reads.
+    struct Trickle<'a> {
+        bytes: &'a [u8],
+        step: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+            let len = cmp::min(cmp::min(self.step, buffer.len()), self.bytes.len());
+            buffer[..len].copy_from_slice(&self.bytes[..len]);
+            self.bytes = &self.bytes[len..];
+            Ok(len)
+        }
+    }
+
+    /// Everything sent, then the final message.
+    fn load_all(bytes: &[u8], step: usize) -> (Vec<Loaded>, Loaded) {
+        let (sender, receiver) = mpsc::channel();
+        let reader = Trickle { bytes, step };
+        let last = load(reader, || Ok(bytes.to_vec()), &sender, &AtomicBool::new(false)).unwrap();
+        (receiver.try_iter().collect(), last)
+    }
+
+    fn lines(messages: &[Loaded]) -> (Vec<String>, u64) {
+        let (mut all, mut total) = (Vec::new(), 0);
+        for message in messages {
+            if let Loaded::Lines(lines, bytes) = message {
+                all.extend(lines.iter().cloned());
+                total += bytes;
+            }
+        }
+        (all, total)
+    }
+
+    #[test]
+    fn lines_across_reads() {
+        let text = "first line\nsecond\n\nlast without newline";
+        let expected: Vec<String> = text.lines().map(String::from).collect();
+        for step in [1, 3, 7, text.len()] {
+            let (messages, last) = load_all(text.as_bytes(), step);
+            assert!(matches!(last, Loaded::Done));
+            assert_eq!(lines(&messages), (expected.clone(), text.len() as u64));
+        }
+    }
+
+    #[test]
+    fn chunks_bigger_than_a_read() {
+        let text = "x".repeat(CHUNK_SIZE * 2 + 10) + "\ny\n";
+        let (messages, _) = load_all(text.as_bytes(), CHUNK_SIZE);
+        let (rows, bytes) = lines(&messages);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].len(), CHUNK_SIZE * 2 + 10);
+        assert_eq!(bytes, text.len() as u64);
+    }
+
+    #[test]
+    fn line_ending_comes_first() {
+        let (messages, _) = load_all(b"a\r\nb\r\n", 2);
+        assert!(matches!(messages.first(), Some(Loaded::LineEnding(LineEnding::Crlf))));
+        assert_eq!(lines(&messages).0, vec!["a", "b"]);
+        let (messages, _) = load_all(b"a\nb\r\n", 64);
+        assert!(matches!(messages.first(), Some(Loaded::LineEnding(LineEnding::Lf))));
+    }
+
+    #[test]
+    fn nul_bytes_make_it_binary() {
+        let bytes = b"text\nmore\n\0tail";
+        let (_, last) = load_all(bytes, 64);
+        assert!(matches!(last, Loaded::Binary(all) if all == bytes));
+        // even when the text before it was already sent
+        let (messages, last) = load_all(bytes, 5);
+        assert_eq!(lines(&messages).0, vec!["text", "more"]);
+        assert!(matches!(last, Loaded::Binary(all) if all == bytes));
+    }
+
+    #[test]
+    fn invalid_utf8_makes_it_binary() {
+        let (_, last) = load_all(b"ok\n\xff\xfe\n", 64);
+        assert!(matches!(last, Loaded::Binary(_)));
+        let (_, last) = load_all(b"ok\n\xc3", 64);
+        assert!(matches!(last, Loaded::Binary(_)));
+    }
+
+    #[test]
+    fn stops_when_cancelled() {
+        let (sender, receiver) = mpsc::channel();
+        let reader = Trickle { bytes: b"a\nb\n", step: 1 };
+        let last = load(reader, || Ok(Vec::new()), &sender, &AtomicBool::new(true)).unwrap();
+        assert!(matches!(last, Loaded::Done));
+        assert_eq!(receiver.try_iter().count(), 0);
+    }
+}
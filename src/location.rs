@@ -0,0 +1,178 @@
+use crate::unicode;
+
+/// One file:line entry, and what produced it.
+pub struct Location {
+    pub file: String,
+    pub row: usize,
+    /// Grapheme index in the row, for the cursor.
+    pub column: usize,
+    pub source: &'static str,
+    pub message: String,
+}
+
+/// The file:line list that TODO scanning (and anything else producing
+/// locations) fills in, walked with next and previous.
+pub struct LocationList {
+    entries: Vec<Location>,
+    current: Option<usize>,
+}
+
+impl LocationList {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            current: None,
+        }
+    }
+
+    /// Replaces whatever `source` pushed before with `entries`.
+    pub fn replace(&mut self, source: &'static str, entries: Vec<Location>) {
+        self.entries.retain(|entry| entry.source != source);
+        self.entries.extend(entries);
+        self.current = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.current = None;
+    }
+
+    pub fn entries(&self) -> &[Location] {
+        &self.entries
+    }
+
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    pub fn select(&mut self, at: usize) -> Option<&Location> {
+        self.current = Some(at);
+        self.entries.get(at)
+    }
+
+    /// Steps to the next or previous entry. Also says whether it went past
+    /// an end and wrapped around.
+    pub fn step(&mut self, forward: bool) -> Option<(usize, bool)> {
+        let len = self.entries.len();
+        if len == 0 {
+            return None;
+        }
+        let (at, wrapped) = match (self.current, forward) {
+            (None, true) => (0, false),
+            (None, false) => (len - 1, false),
+            (Some(at), true) => ((at + 1) % len, at + 1 == len),
+            (Some(at), false) => ((at + len - 1) % len, at == 0),
+        };
+        self.current = Some(at);
+        Some((at, wrapped))
+    }
+}
+
+const TODO_WORDS: [&str; 4] = ["TODO", "FIXME", "XXX", "HACK"];
+
+/// TODO, FIXME, XXX and HACK notes among `lines`, as whole words.
+pub fn todos<'a>(file: &str, lines: impl Iterator<Item = &'a str>) -> Vec<Location> {
+    let is_word = |ch: char| ch.is_alphanumeric() || ch == '_';
+    lines
+        .enumerate()
+        .filter_map(|(row, line)| {
+            let at = TODO_WORDS
+                .iter()
+                .filter_map(|word| {
+                    line.match_indices(word).map(|(at, _)| at).find(|&at| {
+                        let before = line[..at].chars().next_back();
+                        let after = line[at + word.len()..].chars().next();
+                        !before.is_some_and(is_word) && !after.is_some_and(is_word)
+                    })
+                })
+                .min()?;
+            Some(Location {
+                file: file.to_string(),
+                row,
+                column: unicode::graphemes(&line[..at]).count(),
+                source: "todo",
+                message: line[at..].trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(source: &'static str, row: usize) -> Location {
+        Location {
+            file: String::from("main.rs"),
+            row,
+            column: 0,
+            source,
+            message: String::new(),
+        }
+    }
+
+    fn rows(list: &LocationList) -> Vec<(&str, usize)> {
+        list.entries().iter().map(|entry| (entry.source, entry.row)).collect()
+    }
+
+    #[test]
+    fn stepping_wraps_around() {
+        let mut list = LocationList::new();
+        assert_eq!(list.step(true), None);
+        list.replace("todo", vec![location("todo", 1), location("todo", 2), location("todo", 3)]);
+        assert_eq!(list.step(true), Some((0, false)));
+        assert_eq!(list.step(true), Some((1, false)));
+        assert_eq!(list.step(true), Some((2, false)));
+        assert_eq!(list.step(true), Some((0, true)));
+        assert_eq!(list.step(false), Some((2, true)));
+        assert_eq!(list.step(false), Some((1, false)));
+        // from nowhere, backwards starts at the end
+        list.clear();
+        list.replace("todo", vec![location("todo", 1), location("todo", 2)]);
+        assert_eq!(list.step(false), Some((1, false)));
+        list.select(0);
+        assert_eq!(list.step(false), Some((1, true)));
+        assert_eq!(list.current(), Some(1));
+    }
+
+    #[test]
+    fn replacing_keeps_other_sources() {
+        let mut list = LocationList::new();
+        list.replace("todo", vec![location("todo", 1), location("todo", 2)]);
+        list.replace("grep", vec![location("grep", 7)]);
+        list.step(true);
+        list.replace("todo", vec![location("todo", 5)]);
+        assert_eq!(rows(&list), [("grep", 7), ("todo", 5)]);
+        assert_eq!(list.current(), None);
+        list.replace("todo", Vec::new());
+        assert_eq!(rows(&list), [("grep", 7)]);
+    }
+
+    /// (row, column, message) of the notes in `text`.
+    fn notes(text: &str) -> Vec<(usize, usize, String)> {
+        todos("main.rs", text.lines()).into_iter().map(|entry| (entry.row, entry.column, entry.message)).collect()
+    }
+
+    #[test]
+    fn notes_are_whole_words() {
+        let text = "// TODO: one\nTODOS are not\n_TODO nor\nMY_FIXME nor\nFIXME2 nor\n(XXX) yes\nHACK";
+        let rows: Vec<usize> = notes(text).into_iter().map(|(row, _, _)| row).collect();
+        assert_eq!(rows, [0, 5, 6]);
+        // a later whole word after a part of one on the same line
+        assert_eq!(notes("TODOS and TODO"), [(0, 10, String::from("TODO"))]);
+    }
+
+    #[test]
+    fn the_first_note_on_a_line() {
+        assert_eq!(notes("x = 1 # FIXME then TODO"), [(0, 8, String::from("FIXME then TODO"))]);
+        assert_eq!(notes("x = 1 # TODO then FIXME"), [(0, 8, String::from("TODO then FIXME"))]);
+    }
+
+    #[test]
+    fn columns_count_graphemes() {
+        // the letters before aren't ASCII, and e + combining acute is one
+        assert_eq!(notes("\u{4e2d}\u{6587} e\u{301} TODO fix"), [(0, 5, String::from("TODO fix"))]);
+        // nor is a letter right before a note a word boundary
+        assert!(notes("\u{e9}TODO").is_empty());
+    }
+}
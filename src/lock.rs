@@ -0,0 +1,64 @@
+// Edit locks left next to a file by editors that take them: `.#name`, a
+// symlink whose target says who holds the lock, `user@host.pid:boot` (the
+// convention Emacs and others follow). The viewer never saves, so it only
+// reads locks and never takes one.
+
+use std::fs;
+use std::path::Path;
+
+pub struct Holder {
+    pub user: String,
+    pub host: String,
+    pub pid: Option<u32>,
+}
+
+impl Holder {
+    /// The process is on this machine and no longer running, so the lock
+    /// is left over from a crash.
+    #[cfg(unix)]
+    pub fn is_stale(&self) -> bool {
+        let pid = match self.pid {
+            Some(pid) => pid,
+            None => return false,
+        };
+        // signal 0 only checks whether the process exists
+        host_name().is_some_and(|host| host == self.host)
+            && unsafe { libc::kill(pid as libc::pid_t, 0) } != 0
+            && std::io::Error::last_os_error().raw_os_error() == Some(libc::ESRCH)
+    }
+
+    #[cfg(not(unix))]
+    pub fn is_stale(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(unix)]
+fn host_name() -> Option<String> {
+    let mut buffer = [0u8; 256];
+    if unsafe { libc::gethostname(buffer.as_mut_ptr() as *mut libc::c_char, buffer.len()) } != 0 {
+        return None;
+    }
+    let end = buffer.iter().position(|&byte| byte == 0).unwrap_or(buffer.len());
+    Some(String::from_utf8_lossy(&buffer[..end]).into_owned())
+}
+
+/// Who holds the lock on `path`, if anyone does.
+pub fn holder(path: &Path) -> Option<Holder> {
+    let name = path.file_name()?.to_str()?;
+    let lock = path.with_file_name(format!(".#{}", name));
+    // usually a dangling symlink, which is why it isn't read as a file
+    let target = fs::read_link(&lock).ok().or_else(|| fs::read_to_string(&lock).ok().map(Into::into))?;
+    let target = target.to_string_lossy();
+    let (user, rest) = target.split_once('@')?;
+    let rest = rest.split(':').next().unwrap_or(rest);
+    let (host, pid) = match rest.rsplit_once('.') {
+        Some((host, pid)) if pid.parse::<u32>().is_ok() => (host, pid.parse().ok()),
+        _ => (rest, None),
+    };
+    Some(Holder {
+        user: user.to_string(),
+        host: host.to_string(),
+        pid,
+    })
+}
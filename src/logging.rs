@@ -0,0 +1,85 @@
+// Opt-in debug log. The screen belongs to the editor, so everything goes to
+// a file: RTE_LOG=/tmp/rte.log turns it on, and RTE_LOG_LEVEL picks the
+// level (error, warn, info, debug, trace; debug by default) or "keys" to
+// record nothing but key events, for attaching to keyboard bug reports.
+
+use log::{LevelFilter, Log, Metadata, Record};
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Target used for key events.
+pub const KEYS: &str = "keys";
+
+struct FileLogger {
+    file: Mutex<File>,
+    level: LevelFilter,
+    keys_only: bool,
+}
+
+impl Log for FileLogger {
+    /// Only the editor's own records; dependencies like mio log too.
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let target = metadata.target();
+        if self.keys_only {
+            target == KEYS
+        } else {
+            metadata.level() <= self.level && (target == KEYS || target.starts_with(env!("CARGO_CRATE_NAME")))
+        }
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let seconds = now.as_secs() % 86400;
+        let mut file = match self.file.lock() {
+            Ok(file) => file,
+            Err(_) => return,
+        };
+        let _ = writeln!(
+            file,
+            "{:02}:{:02}:{:02}.{:03} {:<5} {}: {}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+            now.subsec_millis(),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// Sets up the file logger if RTE_LOG asks for one. Until then, and when
+/// it doesn't, log macros stop at the max-level check before formatting
+/// anything.
+pub fn init() -> std::io::Result<()> {
+    let path = match env::var_os("RTE_LOG") {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let level = env::var("RTE_LOG_LEVEL").unwrap_or_default();
+    let keys_only = level == KEYS;
+    let level = level.parse().unwrap_or(LevelFilter::Debug);
+    let logger = FileLogger {
+        file: Mutex::new(file),
+        level,
+        keys_only,
+    };
+    if log::set_logger(Box::leak(Box::new(logger))).is_ok() {
+        log::set_max_level(level);
+    }
+    log::info!("rte {} started", env!("CARGO_PKG_VERSION"));
+    Ok(())
+}
@@ -1,164 +1,2243 @@
-use crossterm::{terminal, event, execute, cursor, queue};
+use crossterm::{terminal, event, execute, cursor, queue, style};
 use std::io::{self, Write};
 use crossterm::event::*;
 use crossterm::terminal::ClearType;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::io::stdout;
-use std::path::Path;
-use std::{cmp, env, fs};
+use std::path::{Path, PathBuf};
+use std::collections::{BTreeSet, HashMap};
+use std::{cmp, env, fs, panic};
+use args::{Args, Command, Input};
+use conflict::{Conflict, Section};
+use diffview::{DiffView, Kind};
+use editorconfig::Property;
+use git::{Change, GitGutter};
+use hex::HexView;
+use indent::Indentation;
+use keymap::Action;
+use loader::{LineEnding, Loaded, Loader, Source};
+use jumplist::{JumpList, Position};
+use location::{Location, LocationList};
+use perf::FrameStats;
+use picker::{Picked, Picker};
+use session::Session;
+use spell::Dictionary;
+use statusline::StatusLine;
+use tags::{Address, Tag};
+use watch::Watch;
 
-struct Editor {
-    reader: Reader,
+mod args;
+mod conflict;
+mod diff;
+mod diffview;
+mod editorconfig;
+mod filetype;
+mod git;
+mod hex;
+mod indent;
+mod jumplist;
+mod keymap;
+mod loader;
+mod location;
+mod lock;
+mod logging;
+mod modeline;
+mod perf;
+mod picker;
+mod session;
+mod signals;
+mod spell;
+mod statusline;
+mod tags;
+mod unicode;
+mod watch;
+
+const TAB_STOP: usize = 8;
+const CHECKPOINT_INTERVAL: usize = 1024;
+/// Smallest terminal (columns, rows including the message bar) that the
+/// editor draws into; anything smaller just gets a notice.
+const MIN_SIZE: (usize, usize) = (10, 3);
+/// How often to check on the loader and the git diff while they run.
+const BACKGROUND_POLL: Duration = Duration::from_millis(50);
+/// How soon a key has to follow an Esc to make an Alt combination with it,
+/// when RTE_ESC_ALT is set.
+const ESC_ALT_DELAY: Duration = Duration::from_millis(30);
+
+struct Editor<S: EventSource = Terminal> {
+    reader: Reader<S>,
     output: Output,
+    /// The first key of a chord, while waiting for the second.
+    prefix: Option<KeyEvent>,
 }
 
 impl Editor {
-    fn new() -> Self {
+    fn new(args: Args) -> Self {
         Self {
-            reader: Reader,
-            output: Output::new(),
+            reader: Reader::new(),
+            output: Output::new(args),
+            prefix: None,
         }
     }
+}
 
-    fn process_keypress(&mut self) -> crossterm::Result<bool> {
-        match self.reader.read_key() ? {
-            KeyEvent {
-                code: KeyCode::Char('q'),
-                modifiers: event::KeyModifiers::CONTROL,
-            } => return Ok(false),
-            KeyEvent {
-                code: 
-                    direction 
-                    @ 
-                    (KeyCode::Up 
-                     | KeyCode::Down 
-                     | KeyCode::Left 
-                     | KeyCode::Right
-                     | KeyCode::Home
-                     | KeyCode::End
-                     ),
-                modifiers: KeyModifiers::NONE,
-            } => self.output.move_cursor(direction),
-            KeyEvent {
-                code: val @ (KeyCode::PageUp | KeyCode::PageDown),
-                modifiers: KeyModifiers::NONE
-            } => {
-                (0..self.output.win_size.1).for_each(|_| {
-                    self.output.move_cursor( if matches!(val, KeyCode::PageUp){
-                        KeyCode::Up
-                    } else {
-                        KeyCode::Down
-                    })
-                })
+impl<S: EventSource> Editor<S> {
+
+    fn process_keypress(&mut self, key_event: KeyEvent) -> crossterm::Result<bool> {
+        log::debug!(target: logging::KEYS, "{:?} {:?}", key_event.code, key_event.modifiers);
+        if self.output.is_too_small() {
+            return Ok(key_event.code != KeyCode::Char('q') || key_event.modifiers != KeyModifiers::CONTROL);
+        }
+        if self.output.picker.is_some() {
+            self.output.picker_key(key_event);
+            return Ok(true);
+        }
+        if self.output.prompt.is_some() {
+            self.output.prompt_key(key_event);
+            return Ok(true);
+        }
+        let action = match self.prefix.take() {
+            Some(_) if key_event.code == KeyCode::Esc => {
+                self.output.status_message.clear();
+                return Ok(true);
             }
-            _ => {}
+            Some(prefix) => match keymap::chord(prefix, key_event) {
+                Some(action) => {
+                    self.output.status_message.clear();
+                    action
+                }
+                None => {
+                    let chord = format!(
+                        "{} {}",
+                        keymap::key_name(prefix.code, prefix.modifiers),
+                        keymap::key_name(key_event.code, key_event.modifiers)
+                    );
+                    self.output.status_message.set_message(format!("Undefined: {}", chord));
+                    return Ok(true);
+                }
+            },
+            None if keymap::is_prefix(key_event) => {
+                self.prefix = Some(key_event);
+                let name = keymap::key_name(key_event.code, key_event.modifiers);
+                self.output.status_message.set_message(format!("{}-", name));
+                return Ok(true);
+            }
+            None => match keymap::action(key_event) {
+                Some(action) => action,
+                None => return Ok(true),
+            },
+        };
+        match action {
+            Action::Quit => return Ok(false),
+            #[cfg(unix)]
+            Action::Suspend => self.suspend()?,
+            Action::Help => self.output.open_help(),
+            Action::Move(direction) => self.output.move_cursor(direction),
+            Action::PageUp | Action::PageDown => {
+                let direction = if action == Action::PageUp { KeyCode::Up } else { KeyCode::Down };
+                (0..self.output.win_size.1).for_each(|_| self.output.move_cursor(direction))
+            }
+            Action::ScrollDown => self.output.scroll_view(1, false),
+            Action::ScrollUp => self.output.scroll_view(-1, false),
+            Action::HalfPageDown => self.output.scroll_view(self.output.win_size.1 as isize / 2, true),
+            Action::HalfPageUp => self.output.scroll_view(-(self.output.win_size.1 as isize / 2), true),
+            Action::GoTo => self.output.open_go_to_prompt(),
+            Action::NextChange => self.output.jump_to_change(true),
+            Action::PreviousChange => self.output.jump_to_change(false),
+            Action::NextConflict => self.output.jump_to_conflict(true),
+            Action::PreviousConflict => self.output.jump_to_conflict(false),
+            Action::GoToDefinition => self.output.go_to_definition(),
+            Action::PopTag => self.output.pop_tag(),
+            Action::JumpBack => self.output.walk_jumps(true),
+            Action::JumpForward => self.output.walk_jumps(false),
+            Action::Outline => self.output.open_outline(),
+            Action::Locations => self.output.open_locations(),
+            Action::NextLocation => self.output.step_location(true),
+            Action::PreviousLocation => self.output.step_location(false),
+            Action::FindTodos => self.output.find_todos(),
+            Action::ToggleBookmark => self.output.toggle_bookmark(),
+            Action::NextBookmark => self.output.jump_to_bookmark(true),
+            Action::PreviousBookmark => self.output.jump_to_bookmark(false),
+            Action::ClearBookmarks => self.output.ask_to_clear_bookmarks(),
+            Action::Fold => self.output.fold(),
+            Action::Unfold => self.output.unfold(),
+            Action::FoldAll => self.output.fold_all(),
+            Action::UnfoldAll => self.output.unfold_all(),
+            Action::ToggleScrollbar => self.output.toggle_scrollbar(),
+            Action::DescribeCharacter => self.output.describe_character(),
+            Action::ListInvisible => self.output.list_invisible_characters(),
+            Action::ShowSettings => self.output.show_settings(),
+            Action::ToggleSpelling => self.output.toggle_spelling(),
+            Action::SuggestSpelling => self.output.suggest_spelling(),
+            Action::AddToDictionary => self.output.add_to_dictionary(),
+            Action::ToggleFrameStats => self.output.toggle_frame_stats(),
         }
         Ok(true)
     }
     
+    /// Hands the terminal back and stops the process group, as the shell
+    /// expects from Ctrl-Z. kill() returns once we get SIGCONT; the
+    /// window size is re-read on the next redraw anyway.
+    #[cfg(unix)]
+    fn suspend(&mut self) -> crossterm::Result<()> {
+        CleanUp::restore_terminal()?;
+        log::debug!("suspending");
+        unsafe {
+            libc::kill(0, libc::SIGTSTP);
+        }
+        log::debug!("resumed");
+        CleanUp::set_up_terminal()?;
+        if CleanUp::use_title() {
+            self.output.set_title()?;
+        }
+        self.output.cursor_shape = None;
+        Output::clear_screen()
+    }
+
+    /// Draws, then sleeps until a key arrives, the terminal changes, or
+    /// something the screen depends on is due (see Output::timeout).
     fn run(&mut self) -> crossterm::Result<bool> {
         self.output.refresh_screen()?;
-        self.process_keypress()
+        let key_event = loop {
+            match self.reader.read_event(self.output.timeout())? {
+                Some(Event::Key(key_event)) => break key_event,
+                Some(_) => return Ok(true),
+                None if signals::received().is_some() || self.output.tick() => return Ok(true),
+                None => {}
+            }
+        };
+        if !self.process_keypress(key_event)? {
+            return Ok(false);
+        }
+        // apply everything that queued up while we were busy before drawing
+        // again, so key repeat doesn't leave the screen lagging behind
+        while let Some(event) = self.reader.read_event(Some(Duration::ZERO))? {
+            if let Event::Key(key_event) = event {
+                if !self.process_keypress(key_event)? {
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(true)
     }
 }
 
-struct Reader;
+/// Where terminal events come from: the terminal itself, or a script of
+/// events in tests.
+trait EventSource {
+    /// Whether an event arrives within `timeout`.
+    fn poll(&mut self, timeout: Duration) -> crossterm::Result<bool>;
+    /// The next event, waiting for it as long as it takes.
+    fn read(&mut self) -> crossterm::Result<Event>;
+}
+
+struct Terminal;
+
+impl EventSource for Terminal {
+    fn poll(&mut self, timeout: Duration) -> crossterm::Result<bool> {
+        event::poll(timeout)
+    }
+
+    fn read(&mut self) -> crossterm::Result<Event> {
+        event::read()
+    }
+}
+
+struct Reader<S: EventSource = Terminal> {
+    source: S,
+    /// Set from RTE_ESC_ALT, for terminals whose Alt-x can arrive as an Esc
+    /// and an x in separate reads, too far apart for crossterm to pair.
+    esc_alt: bool,
+    /// What was read while looking for a key after an Esc.
+    pending: Option<Event>,
+}
 
 impl Reader {
-    fn read_key(&self) -> crossterm::Result<KeyEvent> {
-        loop {
-            if event::poll(Duration::from_millis(500))? {
-                if let Event::Key(event) = event::read() ? {
-                    return Ok(event);
+    fn new() -> Self {
+        Reader::with_source(Terminal, env::var_os("RTE_ESC_ALT").is_some())
+    }
+}
+
+impl<S: EventSource> Reader<S> {
+    fn with_source(source: S, esc_alt: bool) -> Self {
+        Self {
+            source,
+            esc_alt,
+            pending: None,
+        }
+    }
+
+    /// The next terminal event, or None once `timeout` passes. Without a
+    /// timeout this blocks until there is one. A signal to quit that came
+    /// in before the wait means None straight away; one during it arrives
+    /// as a resize.
+    fn read_event(&mut self, timeout: Option<Duration>) -> crossterm::Result<Option<Event>> {
+        if let Some(event) = self.pending.take() {
+            return Ok(Some(event));
+        }
+        if signals::received().is_some() {
+            return Ok(None);
+        }
+        let event = match timeout {
+            Some(timeout) if !self.source.poll(timeout)? => return Ok(None),
+            _ => self.source.read()?,
+        };
+        let esc = Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        if !self.esc_alt || event != esc || !self.source.poll(ESC_ALT_DELAY)? {
+            return Ok(Some(event));
+        }
+        // a bare Esc still cancels: only a character right behind it pairs up
+        match self.source.read()? {
+            Event::Key(KeyEvent {
+                code: code @ KeyCode::Char(_),
+                modifiers,
+            }) if !modifiers.contains(KeyModifiers::CONTROL) => {
+                Ok(Some(Event::Key(KeyEvent::new(code, modifiers | KeyModifiers::ALT))))
+            }
+            next => {
+                self.pending = Some(next);
+                Ok(Some(event))
+            }
+        }
+    }
+}
+
+struct StatusMessage {
+    message: Option<String>,
+    set_time: Option<Instant>,
+}
+
+impl StatusMessage {
+    fn new() -> Self {
+        Self {
+            message: None,
+            set_time: None,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.message = None;
+        self.set_time = None;
+    }
+
+    fn set_message(&mut self, message: String) {
+        self.message = Some(message);
+        self.set_time = Some(Instant::now())
+    }
+
+    fn time_left(&self) -> Option<Duration> {
+        let time = self.set_time?;
+        Some(Duration::from_secs(5).saturating_sub(time.elapsed()))
+    }
+
+    fn message(&mut self) -> Option<&String> {
+        let time = self.set_time?;
+        if time.elapsed() > Duration::from_secs(5) {
+            self.message = None;
+            self.set_time = None;
+            None
+        } else {
+            self.message.as_ref()
+        }
+    }
+}
+
+enum PromptAction {
+    GoTo,
+    /// Asks before removing every bookmark in the file; "y" goes ahead.
+    ClearBookmarks,
+}
+
+/// A line of input typed into the message bar. Enter hands it to the
+/// action, Esc throws it away.
+struct Prompt {
+    label: String,
+    input: String,
+    action: PromptAction,
+}
+
+enum PickerAction {
+    Tag(Vec<Tag>),
+    /// The rows of the listed symbols.
+    Outline(Vec<usize>),
+    Help,
+    Locations,
+}
+
+/// Where the cursor goes once a file being opened has loaded.
+enum Jump {
+    Position(usize, usize),
+    /// A position and the row offset it was seen with, from a session.
+    View(usize, usize, usize),
+    /// From a tags file, or +N and +/pattern on the command line.
+    Address(Address),
+}
+
+/// Where a tag jump came from.
+struct TagStackEntry {
+    file_name: Option<String>,
+    cursor_x: usize,
+    cursor_y: usize,
+}
+
+struct Output {
+    win_size: (usize, usize),
+    editor_contents: EditorContents,
+    cursor_controller: CursorController,
+    editor_rows: EditorRows,
+    hex_view: Option<HexView>,
+    diff_view: Option<DiffView>,
+    file_name: Option<String>,
+    git_gutter: GitGutter,
+    loader: Option<Loader>,
+    watch: Option<Watch>,
+    /// Set while reloading a file that changed on disk.
+    reloading: bool,
+    prompt: Option<Prompt>,
+    picker: Option<(Picker, PickerAction)>,
+    pending_jump: Option<Jump>,
+    tag_stack: Vec<TagStackEntry>,
+    jumps: JumpList,
+    locations: LocationList,
+    /// Bookmarked rows, by file name, so they survive opening another file.
+    bookmarks: HashMap<String, BTreeSet<usize>>,
+    /// The rows from before a reload, to move the bookmarks along with
+    /// the lines they were on.
+    reload_rows: Option<Vec<String>>,
+    scrollbar: bool,
+    /// Buffer-local settings picked up from .editorconfig files and
+    /// modelines.
+    settings: Vec<Property>,
+    conflicts: Vec<Conflict>,
+    tab_stop: usize,
+    status_message: StatusMessage,
+    /// Set from RTE_STATUS; without it there is no status bar.
+    status_line: Option<StatusLine>,
+    /// Set from RTE_TITLE; without it the title is the file's name.
+    title: Option<StatusLine>,
+    /// The title last sent, so it's only sent again when it changes.
+    shown_title: String,
+    /// Loaded the first time spelling is checked.
+    dictionary: Option<Dictionary>,
+    /// Misspelled words by row, as byte ranges, while spell checking is on.
+    misspelled: Option<HashMap<usize, Vec<(usize, usize)>>>,
+    /// Likewise the cursor shape; None until one is sent.
+    cursor_shape: Option<cursor::CursorShape>,
+    frame_stats: FrameStats,
+    /// Whether the frame stats are drawn over the top right corner
+    /// (RTE_PERF, or Ctrl-X P).
+    show_frame_stats: bool,
+    /// Where the session is saved on quit, with --session or --restore,
+    /// unless the text came from standard input.
+    session: Option<PathBuf>,
+    /// Set by -R. Nothing is ever written, so it only shows as [RO].
+    readonly: bool,
+    /// How much of the screen scrolling may leave below the last row, from
+    /// 0 to 1 (RTE_SCROLL_PAST_END; all of it by default).
+    scroll_past_end: f64,
+}
+
+impl Output {
+    fn new(args: Args) -> Self {
+        let mut unknown = Vec::new();
+        let mut template = |name: &str| {
+            let (line, mut missing) = StatusLine::parse(&env::var(name).ok()?);
+            unknown.extend(missing.drain(..).map(|placeholder| format!("{} in {}", placeholder, name)));
+            Some(line)
+        };
+        let (status_line, title) = (template("RTE_STATUS"), template("RTE_TITLE"));
+        let win_size = Self::window_size(status_line.is_some());
+        let session = match args.session {
+            // text from a pipe has no file to come back to, and saving
+            // would drop the session's own file from it
+            Some(_) if matches!(args.input, Some(Input::Stdin)) => {
+                log::info!("not using the session for standard input");
+                None
+            }
+            Some(args::Session::Named(name)) => session::path(&name),
+            Some(args::Session::Restore) => {
+                env::current_dir().ok().and_then(|dir| session::path(&session::name_for_dir(&dir)))
+            }
+            None => None,
+        };
+        let file_name = match &args.input {
+            Some(Input::File(file)) => Some(file.clone()),
+            _ => None,
+        };
+        let watch = file_name.as_ref().map(|file| Watch::new(PathBuf::from(file)));
+        let mut output = Self {
+            win_size,
+            editor_contents: EditorContents::new(),
+            cursor_controller: CursorController::new(win_size),
+            editor_rows: EditorRows::new(TAB_STOP),
+            hex_view: None,
+            diff_view: None,
+            file_name,
+            git_gutter: GitGutter::new(),
+            loader: None,
+            watch,
+            reloading: false,
+            prompt: None,
+            picker: None,
+            pending_jump: None,
+            tag_stack: Vec::new(),
+            jumps: JumpList::new(),
+            locations: LocationList::new(),
+            bookmarks: HashMap::new(),
+            reload_rows: None,
+            scrollbar: true,
+            settings: Vec::new(),
+            conflicts: Vec::new(),
+            tab_stop: TAB_STOP,
+            status_message: StatusMessage::new(),
+            status_line,
+            title,
+            shown_title: String::new(),
+            cursor_shape: None,
+            dictionary: None,
+            misspelled: None,
+            scroll_past_end: Self::scroll_past_end(),
+            session,
+            readonly: args.readonly,
+            frame_stats: FrameStats::new(),
+            show_frame_stats: env::var_os("RTE_PERF").is_some(),
+        };
+        if !unknown.is_empty() {
+            log::warn!("unknown placeholders: {}", unknown.join(", "));
+            output.status_message.set_message(format!("Unknown placeholder {}", unknown.join(", ")));
+        }
+        match args.input {
+            Some(Input::Diff(left, right)) => output.open_diff(left, right),
+            Some(Input::Stdin) => output.loader = Some(Loader::spawn(Source::Stdin)),
+            Some(Input::File(_)) | None => {
+                output.restore_session();
+                output.load()
+            }
+        }
+        // wins over where the session left off
+        if let Some(start) = args.start {
+            output.pending_jump = Some(Jump::Address(start));
+        }
+        output
+    }
+
+    /// Puts back the bookmarks saved in the session, and its file and view
+    /// unless another file was given. Missing files are skipped.
+    fn restore_session(&mut self) {
+        let path = match &self.session {
+            Some(path) if path.exists() => path.clone(),
+            _ => return,
+        };
+        let session = match Session::load(&path) {
+            Ok(session) => session,
+            Err(error) => {
+                log::error!("reading {}: {}", path.display(), error);
+                return self.status_message.set_message(format!("Can't read the session: {}", error));
+            }
+        };
+        let mut missing = Vec::new();
+        for (file, rows) in session.bookmarks {
+            if Path::new(&file).exists() {
+                self.bookmarks.insert(file, rows.into_iter().collect());
+            } else {
+                missing.push(file);
+            }
+        }
+        match session.file {
+            Some(file) if self.file_name.is_none() && !Path::new(&file).exists() => missing.push(file),
+            Some(file) if self.file_name.is_none() || self.file_name.as_ref() == Some(&file) => {
+                let (cursor_x, cursor_y) = session.cursor;
+                self.pending_jump = Some(Jump::View(cursor_x, cursor_y, session.row_offset));
+                self.watch = Some(Watch::new(PathBuf::from(&file)));
+                self.file_name = Some(file);
+            }
+            _ => {}
+        }
+        log::info!("restored the session from {}", path.display());
+        if !missing.is_empty() {
+            missing.dedup();
+            self.status_message.set_message(format!("Gone since the session was saved: {}", missing.join(", ")));
+        }
+    }
+
+    fn save_session(&self) {
+        let path = match &self.session {
+            Some(path) => path,
+            None => return,
+        };
+        let mut bookmarks: Vec<(String, Vec<usize>)> =
+            self.bookmarks.iter().map(|(file, rows)| (file.clone(), rows.iter().copied().collect())).collect();
+        bookmarks.sort();
+        let session = Session {
+            file: self.file_name.clone(),
+            cursor: (self.cursor_controller.cursor_x, self.cursor_controller.cursor_y),
+            row_offset: self.cursor_controller.row_offset,
+            bookmarks,
+        };
+        match session.save(path) {
+            Ok(()) => log::info!("saved the session to {}", path.display()),
+            Err(error) => log::error!("saving the session to {}: {}", path.display(), error),
+        }
+    }
+
+    /// `rte --diff <old> <new>`
+    fn open_diff(&mut self, left: String, right: String) {
+        let mut contents = Vec::new();
+        for file in [&left, &right] {
+            match fs::read(file) {
+                Ok(bytes) => contents.push(String::from_utf8_lossy(&bytes).into_owned()),
+                Err(error) => return self.status_message.set_message(format!("Can't read {}: {}", file, error)),
+            }
+        }
+        let diff_view = DiffView::new((left, right), &contents[0], &contents[1]);
+        self.status_message.set_message(format!(
+            "{} \u{2194} {}: {} differences (F5 / Shift-F5 to move between them)",
+            diff_view.names.0,
+            diff_view.names.1,
+            diff_view.number_of_hunks()
+        ));
+        self.diff_view = Some(diff_view);
+    }
+
+    /// (Re)reads the file from scratch, starting with its .editorconfig
+    /// settings.
+    fn load(&mut self) {
+        let file = match &self.file_name {
+            Some(file) => PathBuf::from(file),
+            None => return,
+        };
+        self.settings = editorconfig::properties(&file);
+        self.tab_stop = editorconfig::tab_width(&self.settings).unwrap_or(TAB_STOP);
+        self.editor_rows = EditorRows::new(self.tab_stop);
+        self.hex_view = None;
+        self.git_gutter = GitGutter::new();
+        self.conflicts = Vec::new();
+        self.loader = Some(Loader::spawn(Source::File(file)));
+    }
+
+    /// Replaces the buffer with `file`. The cursor goes to `jump` once
+    /// it's loaded.
+    fn open(&mut self, file: String, jump: Jump) {
+        log::info!("opening {}", file);
+        self.watch = Some(Watch::new(PathBuf::from(&file)));
+        self.file_name = Some(file);
+        self.cursor_controller = CursorController::new(self.win_size);
+        self.reloading = false;
+        self.pending_jump = Some(jump);
+        self.load();
+    }
+
+    /// The buffer can't have unsaved changes, so a file that changed on
+    /// disk is simply reloaded; the cursor stays on the same row. One that
+    /// went missing stays on screen as it was, marked in the status bar,
+    /// until it comes back.
+    fn poll_watch(&mut self) {
+        if self.loader.is_some() {
+            return;
+        }
+        let file = self.file_name.clone().unwrap_or_default();
+        match self.watch.as_mut().and_then(Watch::poll) {
+            Some(watch::Change::Modified) => {
+                log::info!("{} changed on disk, reloading", file);
+                self.reloading = true;
+                if self.bookmarks.contains_key(&file) || self.jumps.has_file(&file) {
+                    self.reload_rows = Some(self.editor_rows.snapshot());
+                }
+                self.load();
+            }
+            Some(watch::Change::Missing(missing)) => {
+                log::warn!("{} is gone: {:?}", file, missing);
+                let message = match missing {
+                    watch::Missing::Deleted => format!("{} was deleted or renamed on disk", file),
+                    watch::Missing::NoPermission => format!("{} can no longer be read: permission denied", file),
+                    watch::Missing::DirectoryGone => format!("The directory {} was in is gone", file),
+                };
+                self.status_message.set_message(message)
+            }
+            None => {}
+        }
+    }
+
+    fn clamp_cursor(&mut self) {
+        let cursor = &mut self.cursor_controller;
+        match &self.hex_view {
+            Some(hex_view) => {
+                cursor.cursor_y = cmp::min(cursor.cursor_y, hex_view.number_of_rows().saturating_sub(1));
+                cursor.cursor_x = cmp::min(cursor.cursor_x, hex_view.row_len(cursor.cursor_y).saturating_sub(1));
+            }
+            None => {
+                let number_of_rows = self.editor_rows.number_of_rows();
+                cursor.cursor_y = cmp::min(cursor.cursor_y, number_of_rows);
+                cursor.cursor_x = if cursor.cursor_y < number_of_rows {
+                    cmp::min(cursor.cursor_x, self.editor_rows.get_row(cursor.cursor_y).len())
+                } else {
+                    0
+                };
+            }
+        }
+    }
+
+    /// Takes whatever the background loader has read so far. The git diff
+    /// and the invisible-character scan need the whole file, so they wait
+    /// until it's all in.
+    fn poll_loader(&mut self) {
+        let (messages, elapsed) = match &mut self.loader {
+            Some(loader) => (loader.poll(), loader.elapsed()),
+            None => return,
+        };
+        let file = self.file_name.clone().unwrap_or_default();
+        for message in messages {
+            match message {
+                Loaded::Lines(lines, _) => self.editor_rows.extend(lines),
+                Loaded::LineEnding(line_ending) => self.editor_rows.line_ending = line_ending,
+                Loaded::Binary(bytes) => {
+                    log::info!("loaded {} as binary ({} bytes) in {:?}", file, bytes.len(), elapsed);
+                    self.loader = None;
+                    self.editor_rows = EditorRows::new(self.tab_stop);
+                    self.hex_view = Some(HexView::new(bytes));
+                    self.status_message
+                        .set_message(format!("{} is binary: showing a read-only hex view", file));
+                    self.finish_reload(&file);
+                }
+                Loaded::Done => {
+                    self.loader = None;
+                    let jump = self.pending_jump.take();
+                    let old_rows = self.reload_rows.take();
+                    if self.hex_view.is_some() {
+                        return;
+                    }
+                    log::info!("loaded {} ({} rows) in {:?}", file, self.editor_rows.number_of_rows(), elapsed);
+                    self.detect_file_type(&file);
+                    self.detect_indentation();
+                    if env::var_os("RTE_NO_MODELINES").is_none() {
+                        self.apply_modelines();
+                    }
+                    if self.file_name.is_some() {
+                        self.git_gutter = GitGutter::spawn(Path::new(&file), self.editor_rows.snapshot());
+                    }
+                    self.conflicts = conflict::scan(self.editor_rows.row_contents.iter().map(|row| &*row.row_content));
+                    let invisible = self.editor_rows.invisible_characters().len();
+                    if !self.conflicts.is_empty() {
+                        let count = self.conflicts.len();
+                        self.status_message.set_message(format!(
+                            "{} has {} merge conflict{} (F2 / Shift-F2 to move between them)",
+                            file,
+                            count,
+                            if count == 1 { "" } else { "s" }
+                        ));
+                    } else if invisible > 0 {
+                        self.status_message.set_message(format!(
+                            "{} contains {} invisible or confusable characters (F4 lists them)",
+                            file, invisible
+                        ));
+                    }
+                    if !self.reloading {
+                        self.report_lock(&file);
+                    }
+                    self.finish_reload(&file);
+                    if let Some(old_rows) = old_rows {
+                        self.follow_changes(&file, old_rows);
+                    }
+                    self.refresh_outline();
+                    if self.misspelled.is_some() {
+                        self.check_spelling();
+                    }
+                    if let Some(jump) = jump {
+                        self.apply_jump(jump);
+                    }
+                }
+                Loaded::Failed(error) => {
+                    log::error!("reading {}: {}", file, error);
+                    self.loader = None;
+                    self.pending_jump = None;
+                    self.reload_rows = None;
+                    self.reloading = false;
+                    self.status_message.set_message(format!("Can't read {}: {}", file, error));
+                }
+            }
+        }
+    }
+
+    /// Says so when another editor holds a lock on the file just opened,
+    /// since the file may be about to change under the viewer.
+    fn report_lock(&mut self, file: &str) {
+        let holder = match lock::holder(Path::new(file)) {
+            Some(holder) => holder,
+            None => return,
+        };
+        let pid = holder.pid.map_or_else(String::new, |pid| format!(" (pid {})", pid));
+        log::info!("{} is locked by {}@{}{}", file, holder.user, holder.host, pid);
+        let message = if holder.is_stale() {
+            format!("{} has a stale edit lock from {}{}, which is no longer running", file, holder.user, pid)
+        } else {
+            format!("{} is being edited by {}@{}{}", file, holder.user, holder.host, pid)
+        };
+        self.status_message.set_message(message);
+    }
+
+    fn finish_reload(&mut self, file: &str) {
+        if self.reloading {
+            self.reloading = false;
+            self.clamp_cursor();
+            self.status_message.set_message(format!("Reloaded {}: it changed on disk", file));
+        }
+    }
+
+    fn detect_file_type(&mut self, file: &str) {
+        let first_line = (self.editor_rows.number_of_rows() > 0)
+            .then(|| &*self.editor_rows.get_row(0).row_content);
+        if let Some((file_type, source)) = filetype::detect(Path::new(file), first_line) {
+            self.settings.push(Property {
+                name: String::from("filetype"),
+                value: String::from(file_type),
+                source: format!("detected from {}", source),
+            });
+        }
+    }
+
+    /// Settings from .editorconfig take precedence over the guess.
+    fn detect_indentation(&mut self) {
+        if self.settings.iter().any(|setting| setting.name == "indent_style") {
+            return;
+        }
+        let lines = self.editor_rows.row_contents.iter().map(|row| &*row.row_content);
+        let (style, size, message) = match indent::detect(lines) {
+            Some(Indentation::Tabs) => ("tab", None, String::from("Indentation: tabs")),
+            Some(Indentation::Spaces(width)) => ("space", Some(width), format!("Indentation: {} spaces", width)),
+            Some(Indentation::Mixed) => ("mixed", None, String::from("Mixed indentation")),
+            None => return,
+        };
+        let source = String::from("detected from contents");
+        self.settings.push(Property {
+            name: String::from("indent_style"),
+            value: String::from(style),
+            source: source.clone(),
+        });
+        if let Some(size) = size {
+            self.settings.push(Property {
+                name: String::from("indent_size"),
+                value: size.to_string(),
+                source,
+            });
+        }
+        self.status_message.set_message(message);
+    }
+
+    fn apply_modelines(&mut self) {
+        let number_of_rows = self.editor_rows.number_of_rows();
+        let mut lines = Vec::new();
+        let head = cmp::min(modeline::MODELINES, number_of_rows);
+        let tail = cmp::max(head, number_of_rows.saturating_sub(modeline::MODELINES));
+        for at in (0..head).chain(tail..number_of_rows) {
+            lines.push((at, &*self.editor_rows.get_row(at).row_content));
+        }
+        let properties = modeline::properties(&lines);
+        if let Some(tab_stop) = properties
+            .iter()
+            .find(|property| property.name == "tabstop")
+            .and_then(|property| property.value.parse().ok())
+        {
+            self.tab_stop = tab_stop;
+            self.editor_rows.set_tab_stop(tab_stop);
+        }
+        for property in properties {
+            self.settings.retain(|setting| setting.name != property.name);
+            self.settings.push(property);
+        }
+    }
+
+    fn show_settings(&mut self) {
+        let mut settings = vec![format!("tab stop {}", self.tab_stop)];
+        for property in &self.settings {
+            settings.push(format!("{}={} ({})", property.name, property.value, property.source));
+        }
+        self.status_message.set_message(settings.join(", "))
+    }
+
+    fn list_invisible_characters(&mut self) {
+        let invisible = self.editor_rows.invisible_characters();
+        let message = if invisible.is_empty() {
+            String::from("No invisible or confusable characters")
+        } else {
+            let positions: Vec<String> = invisible
+                .iter()
+                .map(|(row, column, ch)| format!("{}:{} U+{:04X}", row + 1, column + 1, *ch as u32))
+                .collect();
+            format!("{} invisible characters: {}", invisible.len(), positions.join(", "))
+        };
+        self.status_message.set_message(message)
+    }
+
+    /// How long the main loop can sleep before something on screen needs
+    /// updating; None when only input can change anything.
+    fn timeout(&self) -> Option<Duration> {
+        let mut deadlines = Vec::new();
+        deadlines.extend(self.status_message.time_left());
+        if self.loader.is_some() || self.git_gutter.is_pending() {
+            deadlines.push(BACKGROUND_POLL);
+        }
+        deadlines.extend(self.watch.as_ref().map(Watch::time_left));
+        deadlines.into_iter().min()
+    }
+
+    /// Catches up on background work after a timeout. Returns whether the
+    /// screen needs redrawing.
+    fn tick(&mut self) -> bool {
+        let loading = self.loader.is_some();
+        self.poll_watch();
+        self.poll_loader();
+        let gutter = self.git_gutter.poll();
+        let expired = self.status_message.time_left() == Some(Duration::ZERO);
+        loading || self.loader.is_some() || gutter || expired
+    }
+
+    /// Columns, and rows left for text under the message bar and the status
+    /// bar if there is one.
+    fn window_size(status_bar: bool) -> (usize, usize) {
+        let reserved = 1 + status_bar as usize;
+        terminal::size().map_or((0, 0), |(x, y)| (x as usize, (y as usize).saturating_sub(reserved)))
+    }
+
+    fn scroll_past_end() -> f64 {
+        let value = match env::var("RTE_SCROLL_PAST_END") {
+            Ok(value) => value,
+            Err(_) => return 1.0,
+        };
+        match value.trim().parse::<f64>() {
+            Ok(share) if (0.0..=1.0).contains(&share) => share,
+            _ => {
+                log::warn!("RTE_SCROLL_PAST_END should be between 0 and 1, not {}", value);
+                1.0
+            }
+        }
+    }
+
+    /// Rows below the text: the message bar, and the status bar.
+    fn reserved_rows(&self) -> usize {
+        1 + self.status_line.is_some() as usize
+    }
+
+    /// Picks up terminal resizes before each redraw.
+    fn update_window_size(&mut self) {
+        let win_size = Self::window_size(self.status_line.is_some());
+        if win_size != self.win_size {
+            log::debug!("window resized to {}x{}", win_size.0, win_size.1 + self.reserved_rows());
+        }
+        self.win_size = win_size;
+        self.cursor_controller.screen_row = self.win_size.1;
+        self.cursor_controller.screen_column = self.win_size.0;
+    }
+
+    fn is_too_small(&self) -> bool {
+        Self::too_small(self.win_size.0, self.win_size.1 + self.reserved_rows())
+    }
+
+    /// For a terminal of `columns` by `rows`, counting the bars.
+    fn too_small(columns: usize, rows: usize) -> bool {
+        columns < MIN_SIZE.0 || rows < MIN_SIZE.1
+    }
+
+    /// As much of the notice as fits on the one row there may be.
+    fn too_small_notice(columns: usize) -> String {
+        let notice = format!("window too small (need {}x{})", MIN_SIZE.0, MIN_SIZE.1);
+        String::from(unicode::truncate_to_width(&notice, columns))
+    }
+
+    fn draw_too_small(&mut self) -> crossterm::Result<()> {
+        let start = Instant::now();
+        queue!(self.editor_contents, terminal::Clear(ClearType::All))?;
+        self.editor_contents.push_str(&Self::too_small_notice(self.win_size.0));
+        let bytes = self.editor_contents.len();
+        self.editor_contents.flush()?;
+        self.frame_stats.record(start.elapsed(), bytes, 0);
+        Ok(())
+    }
+
+    fn gutter_width(&self) -> usize {
+        let bookmarked = self.hex_view.is_none() && self.bookmarks().is_some();
+        if self.git_gutter.is_active() || bookmarked { 1 } else { 0 }
+    }
+
+    fn bookmarks(&self) -> Option<&BTreeSet<usize>> {
+        self.bookmarks.get(self.file_name.as_deref()?)
+    }
+
+    fn scrollbar_width(&self) -> usize {
+        if self.scrollbar && self.hex_view.is_none() && self.diff_view.is_none() { 1 } else { 0 }
+    }
+
+    /// Columns left for text between the gutter and the scrollbar.
+    fn text_width(&self) -> usize {
+        self.win_size.0 - self.gutter_width() - self.scrollbar_width()
+    }
+
+    fn toggle_scrollbar(&mut self) {
+        self.scrollbar = !self.scrollbar
+    }
+
+    fn prompt_key(&mut self, key_event: KeyEvent) {
+        let prompt = match &mut self.prompt {
+            Some(prompt) => prompt,
+            None => return,
+        };
+        match key_event {
+            KeyEvent {
+                code: KeyCode::Char(ch),
+                modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+            } => prompt.input.push(ch),
+            KeyEvent {
+                code: KeyCode::Backspace,
+                ..
+            } => {
+                prompt.input.pop();
+            }
+            KeyEvent {
+                code: KeyCode::Esc, ..
+            } => self.prompt = None,
+            KeyEvent {
+                code: KeyCode::Enter,
+                ..
+            } => {
+                if let Some(prompt) = self.prompt.take() {
+                    log::debug!("{}{}", prompt.label, prompt.input);
+                    match prompt.action {
+                        PromptAction::GoTo => self.go_to(prompt.input.trim()),
+                        PromptAction::ClearBookmarks => match prompt.input.trim() {
+                            "y" | "Y" | "yes" => self.clear_bookmarks(),
+                            _ => self.status_message.set_message(String::from("Kept the bookmarks")),
+                        },
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn open_go_to_prompt(&mut self) {
+        let (cursor_x, cursor_y) = (self.cursor_controller.cursor_x, self.cursor_controller.cursor_y);
+        let offset = match &self.hex_view {
+            Some(hex_view) => cmp::min(hex_view.byte_offset(cursor_x, cursor_y), hex_view.len()),
+            None => self.editor_rows.byte_offset(cursor_x, cursor_y),
+        };
+        self.prompt = Some(Prompt {
+            label: format!("Go to byte offset or N% (at byte {}): ", offset),
+            input: String::new(),
+            action: PromptAction::GoTo,
+        });
+    }
+
+    /// Jumps to a byte offset (decimal, or hex with 0x) or a percentage
+    /// of the way through the file by rows, and centers it on screen.
+    fn go_to(&mut self, input: &str) {
+        let from = self.here();
+        let number_of_rows = match &self.hex_view {
+            Some(hex_view) => hex_view.number_of_rows(),
+            None => self.editor_rows.number_of_rows(),
+        };
+        let (cursor_x, cursor_y) = if let Some(percent) = input.strip_suffix('%') {
+            match percent.trim().parse::<usize>() {
+                Ok(percent) => {
+                    let row = number_of_rows.saturating_sub(1) * cmp::min(percent, 100) / 100;
+                    match &self.hex_view {
+                        Some(_) => (0, row),
+                        None => {
+                            self.cursor_controller.move_to_row(row, &self.editor_rows);
+                            (self.cursor_controller.cursor_x, row)
+                        }
+                    }
                 }
+                Err(_) => return self.status_message.set_message(format!("Not a percentage: {}", input)),
+            }
+        } else {
+            let offset = match input.strip_prefix("0x") {
+                Some(hex) => usize::from_str_radix(hex, 16),
+                None => input.parse(),
+            };
+            match (offset, &self.hex_view) {
+                (Ok(offset), Some(hex_view)) => hex_view.position_of_byte(offset),
+                (Ok(offset), None) => self.editor_rows.position_of_byte(offset),
+                (Err(_), _) => return self.status_message.set_message(format!("Not a byte offset: {}", input)),
+            }
+        };
+        self.record_jump(from);
+        self.centre_on(cursor_x, cursor_y);
+    }
+
+    /// Moves the cursor and scrolls so its row is in the middle of the screen.
+    fn centre_on(&mut self, cursor_x: usize, cursor_y: usize) {
+        let visible_row = if self.hex_view.is_some() {
+            cursor_y
+        } else {
+            self.editor_rows.reveal(cursor_y);
+            self.editor_rows.visible_row(cursor_y)
+        };
+        self.cursor_controller.cursor_x = cursor_x;
+        self.cursor_controller.cursor_y = cursor_y;
+        self.cursor_controller.row_offset = visible_row.saturating_sub(self.win_size.1 / 2);
+    }
+
+    fn picker_key(&mut self, key_event: KeyEvent) {
+        if key_event.modifiers.contains(KeyModifiers::CONTROL) {
+            return;
+        }
+        let picked = match &mut self.picker {
+            // q leaves the help unless it's being typed into the filter
+            Some((picker, PickerAction::Help)) if key_event.code == KeyCode::Char('q') && picker.filter.is_empty() => {
+                Some(Picked::Cancelled)
+            }
+            Some((_, PickerAction::Locations)) if key_event.code == KeyCode::Delete => {
+                self.locations.clear();
+                self.status_message.set_message(String::from("Location list cleared"));
+                Some(Picked::Cancelled)
+            }
+            Some((picker, _)) => picker.key(key_event.code),
+            None => return,
+        };
+        let (picked, action) = match (picked, self.picker.take()) {
+            (Some(picked), Some((_, action))) => (picked, action),
+            (None, picker) => return self.picker = picker,
+            _ => return,
+        };
+        match (picked, action) {
+            (Picked::Item(at), PickerAction::Tag(mut tags)) => self.jump_to_tag(tags.swap_remove(at)),
+            // a reload empties the buffer and fills it again under the picker
+            (Picked::Item(at), PickerAction::Outline(rows)) if rows[at] >= self.editor_rows.number_of_rows() => {
+                self.status_message.set_message(String::from("The outline is out of date"))
+            }
+            (Picked::Item(at), PickerAction::Outline(rows)) => {
+                let row = rows[at];
+                self.record_jump(self.here());
+                self.centre_on(self.editor_rows.get_row(row).first_non_blank(), row);
+            }
+            (Picked::Item(at), PickerAction::Locations) => self.go_to_location(at, ""),
+            (Picked::Item(_), PickerAction::Help) | (Picked::Cancelled, _) => {}
+        }
+    }
+
+    /// Every key binding, from the table the keys are dispatched with.
+    /// Typing filters it.
+    fn open_help(&mut self) {
+        let picker = Picker::new(String::from("Keys (Esc or q to close, type to filter)"), keymap::help_lines());
+        self.picker = Some((picker, PickerAction::Help));
+    }
+
+    fn open_locations(&mut self) {
+        let entries = self.locations.entries();
+        if entries.is_empty() {
+            return self.status_message.set_message(String::from("The location list is empty (F3 finds TODOs)"));
+        }
+        let items = entries
+            .iter()
+            .map(|entry| format!("{:<6}{}:{}: {}", entry.source, entry.file, entry.row + 1, entry.message))
+            .collect();
+        let mut picker = Picker::new(String::from("Locations (Del clears)"), items);
+        picker.select(self.locations.current().unwrap_or(0));
+        self.picker = Some((picker, PickerAction::Locations));
+    }
+
+    fn step_location(&mut self, forward: bool) {
+        match self.locations.step(forward) {
+            Some((at, wrapped)) => {
+                let end = if forward { "last" } else { "first" };
+                let note = if wrapped { format!(" (wrapped around past the {})", end) } else { String::new() };
+                self.go_to_location(at, &note);
+            }
+            None => self.status_message.set_message(String::from("The location list is empty")),
+        }
+    }
+
+    fn go_to_location(&mut self, at: usize, note: &str) {
+        let len = self.locations.entries().len();
+        let (file, jump, message) = match self.locations.select(at) {
+            Some(entry) => (
+                entry.file.clone(),
+                Jump::Position(entry.column, entry.row),
+                format!("[{}/{}] {}{}", at + 1, len, entry.message, note),
+            ),
+            None => return,
+        };
+        self.record_jump(self.here());
+        self.go_to_file(file, jump);
+        self.status_message.set_message(message);
+    }
+
+    /// Puts the open file's TODO notes in the location list, in place of
+    /// any found earlier.
+    fn find_todos(&mut self) {
+        let file = match (&self.file_name, &self.hex_view) {
+            (Some(file), None) => file.clone(),
+            _ => return,
+        };
+        let todos = location::todos(&file, self.editor_rows.row_contents.iter().map(|row| &*row.row_content));
+        let count = todos.len();
+        self.locations.replace("todo", todos);
+        self.status_message.set_message(format!(
+            "{} TODO note{} in {} (Ctrl-N / Ctrl-P to visit, Ctrl-L to list)",
+            count,
+            if count == 1 { "" } else { "s" },
+            file
+        ));
+    }
+
+    /// Markdown and plain text. Code is left alone: there's no syntax
+    /// classification to find its comments and strings with.
+    fn is_prose(&self) -> bool {
+        match self.settings.iter().rev().find(|setting| setting.name == "filetype") {
+            Some(setting) => setting.value == "markdown" || setting.value == "text",
+            None => true,
+        }
+    }
+
+    fn toggle_spelling(&mut self) {
+        if self.misspelled.take().is_some() {
+            self.locations.replace("spell", Vec::new());
+            return self.status_message.set_message(String::from("Spell checking off"));
+        }
+        if self.dictionary.is_none() {
+            match Dictionary::load() {
+                Ok(dictionary) => self.dictionary = Some(dictionary),
+                Err(error) => return self.status_message.set_message(format!("Can't read the dictionary: {}", error)),
+            }
+        }
+        self.misspelled = Some(HashMap::new());
+        self.check_spelling();
+    }
+
+    /// Underlines the words the dictionary doesn't know and puts them in
+    /// the location list, in place of those found before.
+    fn check_spelling(&mut self) {
+        let dictionary = match &self.dictionary {
+            Some(dictionary) => dictionary,
+            None => return,
+        };
+        let file = match (&self.file_name, &self.hex_view, &self.diff_view) {
+            (Some(file), None, None) if self.is_prose() => file.clone(),
+            _ => {
+                self.misspelled = Some(HashMap::new());
+                self.locations.replace("spell", Vec::new());
+                return self.status_message.set_message(String::from("Spell checking is for prose: markdown and text"));
+            }
+        };
+        let mut misspelled = HashMap::new();
+        let mut entries = Vec::new();
+        for (at, row) in self.editor_rows.row_contents.iter().enumerate() {
+            let words = dictionary.misspelled(&row.row_content);
+            if words.is_empty() {
+                continue;
+            }
+            entries.extend(words.iter().map(|&(start, end)| Location {
+                file: file.clone(),
+                row: at,
+                column: unicode::graphemes(&row.row_content[..start]).count(),
+                source: "spell",
+                message: row.row_content[start..end].to_string(),
+            }));
+            misspelled.insert(at, words);
+        }
+        let count = entries.len();
+        self.misspelled = Some(misspelled);
+        self.locations.replace("spell", entries);
+        self.status_message.set_message(format!(
+            "{} misspelled word{} (Ctrl-N / Ctrl-P to visit, Ctrl-X = for suggestions)",
+            count,
+            if count == 1 { "" } else { "s" }
+        ));
+    }
+
+    /// The word the cursor is on, by the spell checker's idea of a word.
+    fn spelling_word(&self) -> Option<String> {
+        let cursor_y = self.cursor_controller.cursor_y;
+        if cursor_y >= self.editor_rows.number_of_rows() || self.hex_view.is_some() || self.diff_view.is_some() {
+            return None;
+        }
+        let row = self.editor_rows.get_row(cursor_y);
+        let (byte_index, _) = row.position(self.cursor_controller.cursor_x);
+        spell::words(&row.row_content)
+            .find(|&(start, end)| (start..end).contains(&byte_index))
+            .map(|(start, end)| row.row_content[start..end].to_string())
+    }
+
+    fn suggest_spelling(&mut self) {
+        let (word, dictionary) = match (self.spelling_word(), &self.dictionary) {
+            (Some(word), Some(dictionary)) => (word, dictionary),
+            (None, _) => return self.status_message.set_message(String::from("No word under the cursor")),
+            (_, None) => return self.status_message.set_message(String::from("Spell checking is off (Ctrl-X W)")),
+        };
+        let message = if dictionary.knows(&word) {
+            format!("{} is spelled correctly", word)
+        } else {
+            match dictionary.suggestions(&word) {
+                suggestions if suggestions.is_empty() => format!("No suggestions for {}", word),
+                suggestions => format!("{}: {}", word, suggestions.join(", ")),
+            }
+        };
+        self.status_message.set_message(message);
+    }
+
+    fn add_to_dictionary(&mut self) {
+        let (word, dictionary) = match (self.spelling_word(), &mut self.dictionary) {
+            (Some(word), Some(dictionary)) => (word, dictionary),
+            (None, _) => return self.status_message.set_message(String::from("No word under the cursor")),
+            (_, None) => return self.status_message.set_message(String::from("Spell checking is off (Ctrl-X W)")),
+        };
+        match dictionary.add(&word) {
+            Ok(()) => {
+                self.check_spelling();
+                self.status_message.set_message(format!("Added {} to the personal dictionary", word));
+            }
+            Err(error) => self.status_message.set_message(format!("Can't add {}: {}", word, error)),
+        }
+    }
+
+    fn toggle_bookmark(&mut self) {
+        let cursor_y = self.cursor_controller.cursor_y;
+        let file = match (&self.file_name, &self.hex_view, &self.diff_view) {
+            (Some(file), None, None) if cursor_y < self.editor_rows.number_of_rows() => file.clone(),
+            _ => return,
+        };
+        let bookmarks = self.bookmarks.entry(file.clone()).or_default();
+        let message = if bookmarks.remove(&cursor_y) {
+            format!("Removed the bookmark on row {}", cursor_y + 1)
+        } else {
+            bookmarks.insert(cursor_y);
+            format!("Bookmarked row {} ({} in this file)", cursor_y + 1, bookmarks.len())
+        };
+        if bookmarks.is_empty() {
+            self.bookmarks.remove(&file);
+        }
+        self.status_message.set_message(message);
+    }
+
+    /// Goes to the next or previous bookmark in row order, wrapping around
+    /// at the ends.
+    fn jump_to_bookmark(&mut self, forward: bool) {
+        let cursor_y = self.cursor_controller.cursor_y;
+        let bookmarks = match self.bookmarks() {
+            Some(bookmarks) => bookmarks,
+            None => return self.status_message.set_message(String::from("No bookmarks in this file")),
+        };
+        let row = if forward {
+            bookmarks.range(cursor_y + 1..).next().or_else(|| bookmarks.iter().next())
+        } else {
+            bookmarks.range(..cursor_y).next_back().or_else(|| bookmarks.iter().next_back())
+        };
+        let row = match row {
+            Some(&row) => row,
+            None => return,
+        };
+        let message = format!("Bookmark {} of {}", bookmarks.range(..=row).count(), bookmarks.len());
+        self.record_jump(self.here());
+        self.editor_rows.reveal(row);
+        self.cursor_controller.cursor_y = row;
+        self.cursor_controller.cursor_x = 0;
+        self.status_message.set_message(message);
+    }
+
+    fn ask_to_clear_bookmarks(&mut self) {
+        let count = match self.bookmarks() {
+            Some(bookmarks) => bookmarks.len(),
+            None => return self.status_message.set_message(String::from("No bookmarks in this file")),
+        };
+        self.prompt = Some(Prompt {
+            label: format!("Remove all {} bookmarks in this file? (y/n) ", count),
+            input: String::new(),
+            action: PromptAction::ClearBookmarks,
+        });
+    }
+
+    fn clear_bookmarks(&mut self) {
+        let bookmarks = &mut self.bookmarks;
+        match self.file_name.as_ref().and_then(|file| bookmarks.remove(file)) {
+            Some(bookmarks) => self.status_message.set_message(format!("Removed {} bookmarks", bookmarks.len())),
+            None => self.status_message.set_message(String::from("No bookmarks in this file")),
+        }
+    }
+
+    /// Follows the lines bookmarks and jump list entries were on through
+    /// what changed in the reload. Those on deleted lines go away.
+    fn follow_changes(&mut self, file: &str, old_rows: Vec<String>) {
+        let hunks = diff::diff_lines(&old_rows, &self.editor_rows.snapshot());
+        self.jumps.follow(file, &hunks);
+        if let Some(bookmarks) = self.bookmarks.get_mut(file) {
+            *bookmarks = bookmarks.iter().filter_map(|&row| diff::map_row(&hunks, row)).collect();
+            log::debug!("moved bookmarks through {} hunks: {:?}", hunks.len(), bookmarks);
+            if bookmarks.is_empty() {
+                self.bookmarks.remove(file);
+            }
+        }
+    }
+
+    /// Lists the current file's declarations or headings, as found by the
+    /// per-type rules in filetype::outline.
+    fn open_outline(&mut self) {
+        if self.hex_view.is_some() || self.diff_view.is_some() {
+            return;
+        }
+        let file_type = match self.settings.iter().rev().find(|setting| setting.name == "filetype") {
+            Some(setting) => setting.value.clone(),
+            None => return self.status_message.set_message(String::from("No outline: unknown file type")),
+        };
+        let symbols = filetype::outline(&file_type, self.editor_rows.row_contents.iter().map(|row| &*row.row_content));
+        if symbols.is_empty() {
+            return self.status_message.set_message(format!("Nothing to outline in this {} file", file_type));
+        }
+        let (rows, items): (Vec<usize>, Vec<String>) =
+            symbols.into_iter().map(|(at, text)| (at, format!("{:>5}  {}", at + 1, text))).unzip();
+        let mut picker = Picker::new(String::from("Outline"), items);
+        // start at the symbol the cursor is in
+        let cursor_y = self.cursor_controller.cursor_y;
+        picker.select(rows.iter().rposition(|&row| row <= cursor_y).unwrap_or(0));
+        self.picker = Some((picker, PickerAction::Outline(rows)));
+    }
+
+    /// A reload can move every symbol, so an open outline is built again,
+    /// keeping what was typed into it.
+    fn refresh_outline(&mut self) {
+        let filter = match &self.picker {
+            Some((picker, PickerAction::Outline(_))) => picker.filter.clone(),
+            _ => return,
+        };
+        self.picker = None;
+        self.open_outline();
+        if let Some((picker, _)) = &mut self.picker {
+            filter.chars().for_each(|ch| {
+                picker.key(KeyCode::Char(ch));
+            });
+        }
+    }
+
+    /// Looks the identifier under the cursor up in the nearest `tags` file
+    /// above the open file.
+    fn go_to_definition(&mut self) {
+        if self.hex_view.is_some() || self.diff_view.is_some() {
+            return;
+        }
+        let cursor_y = self.cursor_controller.cursor_y;
+        let word = match (cursor_y < self.editor_rows.number_of_rows())
+            .then(|| self.editor_rows.get_row(cursor_y).word_at(self.cursor_controller.cursor_x))
+            .flatten()
+        {
+            Some(word) => word.to_string(),
+            None => return self.status_message.set_message(String::from("No identifier under the cursor")),
+        };
+        let current_dir = env::current_dir().unwrap_or_default();
+        let dir = self.file_name.as_deref().and_then(|file| Path::new(file).parent()).unwrap_or_else(|| Path::new(""));
+        let tags_file = match tags::find(&current_dir.join(dir)) {
+            Some(tags_file) => tags_file,
+            None => return self.status_message.set_message(String::from("No tags file found")),
+        };
+        let mut tags = match tags::lookup(&tags_file, &word) {
+            Ok(tags) => tags,
+            Err(error) => {
+                return self.status_message.set_message(format!("Can't read {}: {}", tags_file.display(), error))
+            }
+        };
+        log::debug!("{} tags for {} in {}", tags.len(), word, tags_file.display());
+        for tag in &mut tags {
+            if let Ok(relative) = tag.file.strip_prefix(&current_dir) {
+                tag.file = relative.to_path_buf();
+            }
+        }
+        match tags.len() {
+            0 => self.status_message.set_message(format!("No tag for {}", word)),
+            1 => self.jump_to_tag(tags.remove(0)),
+            _ => {
+                let items = tags
+                    .iter()
+                    .map(|tag| {
+                        let address = match &tag.address {
+                            Address::Line(line) => line.to_string(),
+                            Address::Pattern { text, .. } => text.trim().to_string(),
+                        };
+                        match &tag.kind {
+                            Some(kind) => format!("{}:{} [{}]", tag.file.display(), address, kind),
+                            None => format!("{}:{}", tag.file.display(), address),
+                        }
+                    })
+                    .collect();
+                let picker = Picker::new(format!("Definitions of {}", word), items);
+                self.picker = Some((picker, PickerAction::Tag(tags)));
+            }
+        }
+    }
+
+    fn jump_to_tag(&mut self, tag: Tag) {
+        self.record_jump(self.here());
+        self.tag_stack.push(TagStackEntry {
+            file_name: self.file_name.clone(),
+            cursor_x: self.cursor_controller.cursor_x,
+            cursor_y: self.cursor_controller.cursor_y,
+        });
+        let file = tag.file.to_string_lossy().into_owned();
+        self.go_to_file(file, Jump::Address(tag.address));
+    }
+
+    /// Ctrl-T: back to where the last tag jump started.
+    fn pop_tag(&mut self) {
+        match self.tag_stack.pop() {
+            Some(entry) => {
+                let jump = Jump::Position(entry.cursor_x, entry.cursor_y);
+                match entry.file_name {
+                    Some(file) => self.go_to_file(file, jump),
+                    None => self.apply_jump(jump),
+                }
+            }
+            None => self.status_message.set_message(String::from("Tag stack is empty")),
+        }
+    }
+
+    fn here(&self) -> Option<Position> {
+        let file = self.file_name.clone()?;
+        Some(Position {
+            file,
+            cursor_x: self.cursor_controller.cursor_x,
+            cursor_y: self.cursor_controller.cursor_y,
+        })
+    }
+
+    /// Puts where a long jump started on the jump list. The diff view and
+    /// an unnamed buffer have nowhere to come back to.
+    fn record_jump(&mut self, from: Option<Position>) {
+        if let (Some(from), None) = (from, &self.diff_view) {
+            self.jumps.push(from);
+        }
+    }
+
+    /// Ctrl-O and Ctrl-I: back and forward through the jump list, opening
+    /// the file an entry is in if it's another.
+    fn walk_jumps(&mut self, back: bool) {
+        let here = match (self.here(), &self.diff_view) {
+            (Some(here), None) => here,
+            _ => return,
+        };
+        let entry = if back { self.jumps.back(here) } else { self.jumps.forward() };
+        let entry = match entry {
+            Some(entry) => entry.clone(),
+            None => {
+                let message = if back { "No older jumps" } else { "No newer jumps" };
+                return self.status_message.set_message(String::from(message));
+            }
+        };
+        if !Path::new(&entry.file).exists() {
+            self.jumps.remove_current();
+            return self.status_message.set_message(format!("{} no longer exists", entry.file));
+        }
+        let (at, len) = self.jumps.position();
+        self.go_to_file(entry.file, Jump::Position(entry.cursor_x, entry.cursor_y));
+        self.status_message.set_message(format!("Jump {} of {}", at, len));
+    }
+
+    /// Jumps within the open file, or opens `file` first if it's another.
+    fn go_to_file(&mut self, file: String, jump: Jump) {
+        let same_file = match &self.file_name {
+            Some(current) => current == &file || fs::canonicalize(current).ok() == fs::canonicalize(&file).ok(),
+            None => false,
+        };
+        if same_file && self.loader.is_none() {
+            self.apply_jump(jump);
+        } else {
+            self.open(file, jump);
+        }
+    }
+
+    fn apply_jump(&mut self, jump: Jump) {
+        let number_of_rows = self.editor_rows.number_of_rows();
+        let row_offset = match jump {
+            Jump::View(_, _, row_offset) => Some(row_offset),
+            _ => None,
+        };
+        let (cursor_x, cursor_y) = match jump {
+            Jump::Position(cursor_x, cursor_y) | Jump::View(cursor_x, cursor_y, _) => (cursor_x, cursor_y),
+            Jump::Address(Address::Line(line)) => (0, cmp::min(line, number_of_rows).saturating_sub(1)),
+            Jump::Address(address) => {
+                let row = (0..number_of_rows).find(|&at| address.matches(&self.editor_rows.get_row(at).row_content));
+                match row {
+                    Some(row) => (self.editor_rows.get_row(row).first_non_blank(), row),
+                    None => return self.status_message.set_message(String::from("Pattern not found in the file")),
+                }
+            }
+        };
+        self.centre_on(cursor_x, cursor_y);
+        self.clamp_cursor();
+        if let Some(row_offset) = row_offset {
+            self.cursor_controller.row_offset = row_offset;
+        }
+    }
+
+    /// Moves the view by `delta` rows. The cursor moves along with it when
+    /// `move_cursor` is set; otherwise it stays put unless it would leave
+    /// the screen, in which case it's dragged along the edge. scroll_to
+    /// leaves the offset alone as long as the cursor is on screen.
+    fn scroll_view(&mut self, delta: isize, move_cursor: bool) {
+        let total = match (&self.hex_view, &self.diff_view) {
+            (Some(hex_view), _) => hex_view.number_of_rows(),
+            (_, Some(diff_view)) => diff_view.number_of_rows(),
+            _ => self.editor_rows.number_of_visible_rows(),
+        };
+        let last_row = total.saturating_sub(1);
+        let shift = |at: usize| cmp::min((at as isize + delta).max(0) as usize, last_row);
+        let below = (self.win_size.1.saturating_sub(1) as f64 * self.scroll_past_end).round() as usize;
+        let max_offset = (total + below).saturating_sub(self.win_size.1);
+        let row_offset = cmp::min(shift(self.cursor_controller.row_offset), max_offset);
+
+        let cursor_y = self.cursor_controller.cursor_y;
+        let mut visible_row = if self.hex_view.is_some() || self.diff_view.is_some() {
+            cursor_y
+        } else {
+            self.editor_rows.visible_row(cursor_y)
+        };
+        if move_cursor {
+            visible_row = shift(visible_row);
+        }
+        visible_row = visible_row.clamp(row_offset, row_offset + self.win_size.1 - 1);
+        visible_row = cmp::min(visible_row, last_row);
+
+        match &self.hex_view {
+            _ if self.diff_view.is_some() => self.cursor_controller.cursor_y = visible_row,
+            Some(hex_view) => {
+                self.cursor_controller.cursor_y = visible_row;
+                self.cursor_controller.cursor_x =
+                    cmp::min(self.cursor_controller.cursor_x, hex_view.row_len(visible_row).saturating_sub(1));
+            }
+            None if total > 0 => {
+                let file_row = self.editor_rows.file_row(visible_row);
+                if file_row != cursor_y {
+                    self.cursor_controller.move_to_row(file_row, &self.editor_rows);
+                }
+            }
+            None => {}
+        }
+        self.cursor_controller.row_offset = row_offset;
+    }
+
+    fn jump_to_change(&mut self, forward: bool) {
+        let cursor_y = self.cursor_controller.cursor_y;
+        if let Some(diff_view) = &self.diff_view {
+            let target = if forward {
+                diff_view.next_hunk(cursor_y)
+            } else {
+                diff_view.previous_hunk(cursor_y)
+            };
+            return match target {
+                Some(row) => self.cursor_controller.cursor_y = row,
+                None => self.status_message.set_message(String::from("No more differences")),
+            };
+        }
+        let target = if forward {
+            self.git_gutter.next_change(cursor_y)
+        } else {
+            self.git_gutter.previous_change(cursor_y)
+        };
+        match target {
+            Some(row) => {
+                self.editor_rows.reveal(row);
+                self.cursor_controller.cursor_y = row;
+                self.cursor_controller.cursor_x = 0;
+            }
+            None => self.status_message.set_message(String::from("No more changes")),
+        }
+    }
+
+    fn jump_to_conflict(&mut self, forward: bool) {
+        if self.diff_view.is_some() || self.hex_view.is_some() {
+            return;
+        }
+        let cursor_y = self.cursor_controller.cursor_y;
+        let target = if forward {
+            self.conflicts.iter().position(|conflict| conflict.start > cursor_y)
+        } else {
+            self.conflicts.iter().rposition(|conflict| conflict.start < cursor_y)
+        };
+        match target {
+            Some(index) => {
+                let row = self.conflicts[index].start;
+                self.record_jump(self.here());
+                self.editor_rows.reveal(row);
+                self.cursor_controller.cursor_y = row;
+                self.cursor_controller.cursor_x = 0;
+                self.status_message.set_message(format!("Conflict {} of {}", index + 1, self.conflicts.len()));
+            }
+            None if self.conflicts.is_empty() => self.status_message.set_message(String::from("No merge conflicts")),
+            None => self.status_message.set_message(String::from("No more conflicts")),
+        }
+    }
+
+    fn window_title(&self) -> String {
+        if let Some(title) = &self.title {
+            return title.text(&self.status_values());
+        }
+        if let Some(diff_view) = &self.diff_view {
+            let (left, right) = &diff_view.names;
+            return format!("{} \u{2194} {} - rte", left, right);
+        }
+        let name = self
+            .file_name
+            .as_ref()
+            .and_then(|file| Path::new(file).file_name())
+            .map_or_else(|| String::from("[No Name]"), |name| name.to_string_lossy().into_owned());
+        format!("{} - rte", name)
+    }
+
+    fn set_title(&mut self) -> crossterm::Result<()> {
+        let title = self.window_title();
+        queue!(self.editor_contents, terminal::SetTitle(&title))?;
+        self.shown_title = title;
+        self.editor_contents.flush()
+    }
+    
+    fn move_cursor(&mut self, direction: KeyCode) {
+        if let Some(diff_view) = &self.diff_view {
+            return diff_view.move_cursor(&mut self.cursor_controller, direction);
+        }
+        match &self.hex_view {
+            Some(hex_view) => hex_view.move_cursor(&mut self.cursor_controller, direction),
+            None => self.cursor_controller.move_cursor(direction, &self.editor_rows),
+        }
+    }
+
+    fn describe_character(&mut self) {
+        let cursor_x = self.cursor_controller.cursor_x;
+        let cursor_y = self.cursor_controller.cursor_y;
+        let grapheme = if cursor_y < self.editor_rows.number_of_rows() {
+            self.editor_rows.get_row(cursor_y).grapheme(cursor_x)
+        } else {
+            None
+        };
+        let message = match grapheme {
+            None => String::from("No character under the cursor"),
+            Some(grapheme) => {
+                let codepoints: Vec<String> = grapheme
+                    .chars()
+                    .map(|ch| format!("U+{:04X} {}", ch as u32, unicode::describe(ch)))
+                    .collect();
+                let bytes: Vec<String> = grapheme.bytes().map(|byte| format!("{:02x}", byte)).collect();
+                let shown = unicode::control_notation(grapheme).unwrap_or_else(|| grapheme.to_string());
+                format!("'{}' {} (UTF-8: {})", shown, codepoints.join(", "), bytes.join(" "))
+            }
+        };
+        self.status_message.set_message(message)
+    }
+
+    fn fold(&mut self) {
+        if self.hex_view.is_some() {
+            return;
+        }
+        if self.editor_rows.fold_block(self.cursor_controller.cursor_y).is_none() {
+            self.status_message.set_message(String::from("Nothing to fold here"))
+        }
+    }
+
+    fn unfold(&mut self) {
+        if self.hex_view.is_none() && !self.editor_rows.unfold(self.cursor_controller.cursor_y) {
+            self.status_message.set_message(String::from("No fold at the cursor"))
+        }
+    }
+
+    fn fold_all(&mut self) {
+        if self.hex_view.is_some() {
+            return;
+        }
+        let folds = self.editor_rows.fold_all();
+        self.status_message.set_message(format!("Folded {} blocks", folds));
+        let cursor_y = self.cursor_controller.cursor_y;
+        if cursor_y < self.editor_rows.number_of_rows() {
+            let header = self.editor_rows.file_row(self.editor_rows.visible_row(cursor_y));
+            if header != cursor_y {
+                self.cursor_controller.cursor_y = header;
+                self.cursor_controller.cursor_x = 0;
+            }
+        }
+    }
+
+    fn unfold_all(&mut self) {
+        self.editor_rows.unfold_all()
+    }
+
+    /// What the placeholders of RTE_STATUS and RTE_TITLE stand for.
+    fn status_values(&self) -> statusline::Values {
+        let (cursor_x, cursor_y) = (self.cursor_controller.cursor_x, self.cursor_controller.cursor_y);
+        let total_lines = match (&self.hex_view, &self.diff_view) {
+            (Some(hex_view), _) => hex_view.number_of_rows(),
+            (_, Some(diff_view)) => diff_view.number_of_rows(),
+            _ => self.editor_rows.number_of_rows(),
+        };
+        let file_name = match (&self.diff_view, &self.file_name) {
+            (Some(diff_view), _) => format!("{} \u{2194} {}", diff_view.names.0, diff_view.names.1),
+            (None, Some(file)) => file.clone(),
+            (None, None) => String::from("[No Name]"),
+        };
+        let setting = |name: &str| self.settings.iter().rev().find(|setting| setting.name == name);
+        let readonly = self.readonly
+            || setting("readonly").is_some_and(|setting| setting.value == "true")
+            || self.watch.as_ref().is_some_and(Watch::readonly);
+        [
+            file_name,
+            // the buffer can't be modified
+            String::new(),
+            setting("filetype").map(|setting| setting.value.clone()).unwrap_or_default(),
+            (cursor_y + 1).to_string(),
+            (cursor_x + 1).to_string(),
+            total_lines.to_string(),
+            (cmp::min(cursor_y + 1, total_lines) * 100).checked_div(total_lines).unwrap_or(100).to_string(),
+            String::from(if self.hex_view.is_some() { "binary" } else { "utf-8" }),
+            String::from(self.editor_rows.line_ending.name()),
+            String::from(if readonly { "[RO]" } else { "" }),
+            String::from(self.watch.as_ref().and_then(Watch::missing).map_or("", watch::Missing::marker)),
+        ]
+    }
+
+    fn draw_status_bar(&mut self) {
+        let line = match &self.status_line {
+            Some(status_line) => status_line.render(&self.status_values(), self.win_size.0),
+            None => return,
+        };
+        queue!(self.editor_contents, style::SetAttribute(style::Attribute::Reverse)).unwrap();
+        self.editor_contents.push_str(&line);
+        (unicode::str_width(&line)..self.win_size.0).for_each(|_| self.editor_contents.push(' '));
+        queue!(self.editor_contents, style::SetAttribute(style::Attribute::Reset)).unwrap();
+        self.editor_contents.push_str("\r\n");
+    }
+
+    fn draw_message_bar(&mut self) {
+        queue!(
+            self.editor_contents,
+            terminal::Clear(ClearType::UntilNewLine)
+        ).unwrap();
+        if let Some(loader) = &self.loader {
+            let progress = format!(
+                "Loading {}... {}%",
+                self.file_name.as_deref().unwrap_or("standard input"),
+                loader.percent()
+            );
+            self.editor_contents.push_str(unicode::truncate_to_width(&progress, self.win_size.0));
+        } else if let Some(prompt) = &self.prompt {
+            let line = format!("{}{}", prompt.label, prompt.input);
+            self.editor_contents.push_str(unicode::truncate_to_width(&line, self.win_size.0));
+        } else if let Some(msg) = self.status_message.message() {
+            self.editor_contents.push_str(unicode::truncate_to_width(msg, self.win_size.0));
+        }
+    }
+
+    fn clear_screen() -> crossterm::Result<()> {
+        execute!(stdout(), terminal::Clear(ClearType::All))?;
+        execute!(stdout(), cursor::MoveTo(0, 0))
+    }
+
+    fn draw_gutter(&mut self, file_row: usize) {
+        if self.gutter_width() == 0 {
+            return;
+        }
+        let bookmarked = self.bookmarks().is_some_and(|bookmarks| bookmarks.contains(&file_row));
+        let (marker, color) = match self.git_gutter.change(file_row) {
+            _ if bookmarked => ('\u{25b8}', style::Color::Cyan),
+            Some(Change::Added) => ('\u{258e}', style::Color::Green),
+            Some(Change::Modified) => ('\u{258e}', style::Color::Yellow),
+            Some(Change::Deleted) => ('\u{2581}', style::Color::Red),
+            None => (' ', style::Color::Reset),
+        };
+        queue!(self.editor_contents, style::SetForegroundColor(color)).unwrap();
+        self.editor_contents.push(marker);
+        queue!(self.editor_contents, style::SetForegroundColor(style::Color::Reset)).unwrap();
+    }
+
+    /// Git changes that fall on each cell of the scrollbar track.
+    fn scrollbar_markers(&self) -> Vec<Option<Change>> {
+        let screen_row = self.win_size.1;
+        let total = cmp::max(self.editor_rows.number_of_visible_rows(), 1);
+        let mut markers = vec![None; screen_row];
+        for (row, change) in self.git_gutter.markers() {
+            let cell = self.editor_rows.visible_row(row) * screen_row / total;
+            if let Some(marker) = markers.get_mut(cell) {
+                *marker = Some(change);
+            }
+        }
+        markers
+    }
+
+    /// The thumb spans the rows on screen, in proportion to the whole file.
+    fn draw_scrollbar(&mut self, i: usize, marker: Option<Change>) {
+        let screen_row = self.win_size.1;
+        let total = cmp::max(self.editor_rows.number_of_visible_rows(), 1);
+        let thumb_size = cmp::max(screen_row * screen_row / total, 1);
+        let thumb_start = cmp::min(self.cursor_controller.row_offset * screen_row / total, screen_row - cmp::min(thumb_size, screen_row));
+        let in_thumb = total <= screen_row || (thumb_start..thumb_start + thumb_size).contains(&i);
+        let column = self.win_size.0 as u16 - 1;
+        queue!(self.editor_contents, cursor::MoveTo(column, i as u16)).unwrap();
+        let color = match marker {
+            Some(Change::Added) => style::Color::Green,
+            Some(Change::Modified) => style::Color::Yellow,
+            Some(Change::Deleted) => style::Color::Red,
+            None => style::Color::Reset,
+        };
+        queue!(self.editor_contents, style::SetForegroundColor(color)).unwrap();
+        if in_thumb {
+            self.editor_contents.push('\u{2588}');
+        } else if marker.is_some() {
+            self.editor_contents.push('\u{25ac}');
+        } else {
+            queue!(self.editor_contents, style::SetAttribute(style::Attribute::Dim)).unwrap();
+            self.editor_contents.push('\u{2502}');
+            queue!(self.editor_contents, style::SetAttribute(style::Attribute::NormalIntensity)).unwrap();
+        }
+        queue!(self.editor_contents, style::SetForegroundColor(style::Color::Reset)).unwrap();
+    }
+
+    fn draw_grapheme(editor_contents: &mut EditorContents, grapheme: &str) {
+        if let Some(notation) = unicode::control_notation(grapheme) {
+            queue!(editor_contents, style::SetAttribute(style::Attribute::Reverse)).unwrap();
+            editor_contents.push_str(&notation);
+            queue!(editor_contents, style::SetAttribute(style::Attribute::NoReverse)).unwrap();
+        } else if grapheme.chars().any(|ch| unicode::invisible(ch).is_some()) {
+            for ch in grapheme.chars() {
+                if unicode::invisible(ch).is_some() {
+                    queue!(
+                        editor_contents,
+                        style::SetBackgroundColor(style::Color::Yellow),
+                        style::SetForegroundColor(style::Color::Black)
+                    ).unwrap();
+                    editor_contents.push_str(&unicode::invisible_notation(ch));
+                    queue!(
+                        editor_contents,
+                        style::SetBackgroundColor(style::Color::Reset),
+                        style::SetForegroundColor(style::Color::Reset)
+                    ).unwrap();
+                } else {
+                    editor_contents.push(ch);
+                }
+            }
+        } else {
+            editor_contents.push_str(grapheme);
+        }
+    }
+
+    /// Draws the part of `row` from render column `column_offset` that fits
+    /// in `width` columns, and returns how many columns it took.
+    fn draw_row_segment(
+        editor_contents: &mut EditorContents,
+        row: &Row,
+        column_offset: usize,
+        width: usize,
+        underlined: &[(usize, usize)],
+    ) -> usize {
+        let screen_end = column_offset + width;
+        let (start_byte, mut render_column) = row.position_before(column_offset);
+        let mut byte = start_byte;
+        let mut underlining = false;
+        for grapheme in unicode::graphemes(&row.row_content[start_byte..]) {
+            let start = render_column;
+            render_column += row.grapheme_width(grapheme, start);
+            let underline = underlined.iter().any(|&(start, end)| (start..end).contains(&byte));
+            byte += grapheme.len();
+            if render_column <= column_offset {
+                continue;
+            }
+            if underline != underlining {
+                let attribute = if underline { style::Attribute::Underlined } else { style::Attribute::NoUnderline };
+                queue!(editor_contents, style::SetAttribute(attribute)).unwrap();
+                underlining = underline;
+            }
+            if start < column_offset || render_column > screen_end || grapheme == "\t" {
+                for _ in cmp::max(start, column_offset)..cmp::min(render_column, screen_end) {
+                    editor_contents.push(' ');
+                }
+            } else {
+                Self::draw_grapheme(editor_contents, grapheme);
+            }
+            if render_column >= screen_end {
+                break;
+            }
+        }
+        if underlining {
+            queue!(editor_contents, style::SetAttribute(style::Attribute::NoUnderline)).unwrap();
+        }
+        cmp::min(render_column.saturating_sub(column_offset), width)
+    }
+
+    /// One side of a diff row, tinted by the kind of change and padded to
+    /// `width` so the tint and the separator line up.
+    fn draw_diff_pane(&mut self, row: Option<&Row>, kind: Kind, left: bool, width: usize) {
+        let background = match (kind, row) {
+            (_, None) => style::Color::DarkGrey,
+            (Kind::Equal, _) => style::Color::Reset,
+            (Kind::Changed, _) => style::Color::DarkYellow,
+            _ if left => style::Color::DarkRed,
+            _ => style::Color::DarkGreen,
+        };
+        queue!(self.editor_contents, style::SetBackgroundColor(background)).unwrap();
+        let used = match row {
+            Some(row) => {
+                Self::draw_row_segment(&mut self.editor_contents, row, self.cursor_controller.column_offset, width, &[])
+            }
+            None => 0,
+        };
+        for _ in used..width {
+            self.editor_contents.push(' ');
+        }
+        queue!(self.editor_contents, style::SetBackgroundColor(style::Color::Reset)).unwrap();
+    }
+
+    fn draw_diff_rows(&mut self, diff_view: &DiffView) {
+        let width = self.win_size.0;
+        let left_width = (width - 1) / 2;
+        for i in 0..self.win_size.1 {
+            match diff_view.lines.get(i + self.cursor_controller.row_offset) {
+                Some(line) => {
+                    self.draw_diff_pane(line.left.map(|at| &diff_view.left[at]), line.kind, true, left_width);
+                    self.editor_contents.push('\u{2502}');
+                    self.draw_diff_pane(line.right.map(|at| &diff_view.right[at]), line.kind, false, width - 1 - left_width);
+                }
+                None => self.editor_contents.push('~'),
+            }
+            queue!(
+                self.editor_contents,
+                terminal::Clear(ClearType::UntilNewLine)
+            ).unwrap();
+            self.editor_contents.push_str("\r\n");
+        }
+    }
+
+    fn draw_rows(&mut self) {
+        let screen_row = self.win_size.1;
+        let screen_column = self.text_width();
+        let markers = if self.scrollbar_width() > 0 { self.scrollbar_markers() } else { Vec::new() };
+        let start_page = self.start_page(screen_row, screen_column);
+        let start_page_top = screen_row.saturating_sub(start_page.len()) / 3;
+        for i in 0..screen_row {
+            let visible_row = i + self.cursor_controller.row_offset;
+
+            if visible_row >= self.editor_rows.number_of_visible_rows() {
+                match i.checked_sub(start_page_top).and_then(|at| start_page.get(at)) {
+                    Some((line, mut padding)) => {
+                        let line = unicode::truncate_to_width(line, screen_column);
+                        if padding != 0 {
+                            self.editor_contents.push('~');
+                            padding -= 1
+                        }
+                        (0..padding).for_each(|_| self.editor_contents.push(' '));
+
+                        self.editor_contents.push_str(line);
+                    }
+                    None => self.editor_contents.push('~'),
+                }
+            } else {
+                let file_row = self.editor_rows.file_row(visible_row);
+                self.draw_gutter(file_row);
+                let row = self.editor_rows.get_row(file_row);
+                let column_offset = self.cursor_controller.column_offset;
+                let background = conflict::at(&self.conflicts, file_row).map(|conflict| match conflict.section(file_row) {
+                    Section::Marker => style::Color::DarkGrey,
+                    Section::Ours => style::Color::DarkGreen,
+                    Section::Base => style::Color::DarkMagenta,
+                    Section::Theirs => style::Color::DarkBlue,
+                });
+                if let Some(color) = background {
+                    queue!(self.editor_contents, style::SetBackgroundColor(color)).unwrap();
+                }
+                let underlined = self.misspelled.as_ref().and_then(|misspelled| misspelled.get(&file_row));
+                let underlined = underlined.map_or(&[][..], Vec::as_slice);
+                let mut used =
+                    Self::draw_row_segment(&mut self.editor_contents, row, column_offset, screen_column, underlined);
+                if let Some(fold) = self.editor_rows.fold_at(file_row) {
+                    let hidden_rows = fold.hidden_rows();
+                    let placeholder = format!(" \u{2026} [{} line{}]", hidden_rows, if hidden_rows == 1 { "" } else { "s" });
+                    let placeholder = unicode::truncate_to_width(&placeholder, screen_column - used);
+                    used += unicode::str_width(placeholder);
+                    queue!(self.editor_contents, style::SetAttribute(style::Attribute::Reverse)).unwrap();
+                    self.editor_contents.push_str(placeholder);
+                    queue!(self.editor_contents, style::SetAttribute(style::Attribute::NoReverse)).unwrap();
+                }
+                if background.is_some() {
+                    // tint the whole width so the sections read as blocks
+                    (used..screen_column).for_each(|_| self.editor_contents.push(' '));
+                    queue!(self.editor_contents, style::SetBackgroundColor(style::Color::Reset)).unwrap();
+                }
+            }
+            queue!(
+                self.editor_contents,
+                terminal::Clear(ClearType::UntilNewLine)
+            ).unwrap();
+            if let Some(&marker) = markers.get(i) {
+                self.draw_scrollbar(i, marker);
             }
+
+            self.editor_contents.push_str("\r\n");
         }
     }
-}
-
-struct Output {
-    win_size: (usize, usize),
-    editor_contents: EditorContents,
-    cursor_controller: CursorController,
-    editor_rows: EditorRows
-}
 
-impl Output {
-    fn new() -> Self {
-        let win_size = terminal::size()
-            .map(|(x, y)| (x as usize, y as usize))
-            .unwrap();
-        Self {
-            win_size,
-            editor_contents: EditorContents::new(),
-            cursor_controller: CursorController::new(win_size),
-            editor_rows: EditorRows::new()
+    /// What's shown while no file is open: the name and version, then how
+    /// to open a file and the most useful keys, each line with the padding
+    /// that centres it. Too little room leaves just the name.
+    fn start_page(&self, rows: usize, columns: usize) -> Vec<(String, usize)> {
+        if self.file_name.is_some() || self.editor_rows.number_of_rows() > 0 || self.loader.is_some() {
+            return Vec::new();
+        }
+        let centred = |line: &str| (line.to_string(), columns.saturating_sub(unicode::str_width(line)) / 2);
+        let title = String::from("Rust Text Editor");
+        let hints = [
+            (String::from("rte FILE"), "view a file"),
+            (String::from("rte --diff OLD NEW"), "compare two files"),
+            (keymap::key_names(Action::Help), "list the keys"),
+            (keymap::key_names(Action::Quit), "quit"),
+        ];
+        let keys_width = hints.iter().map(|(keys, _)| unicode::str_width(keys)).max().unwrap_or(0);
+        let hints: Vec<String> = hints.iter().map(|(keys, what)| format!("{:<2$}  {}", keys, what, keys_width)).collect();
+        let hints_width = hints.iter().map(|hint| unicode::str_width(hint)).max().unwrap_or(0);
+        if rows < hints.len() + 4 || columns < hints_width + 2 {
+            return vec![centred(&title)];
         }
+        let mut lines = vec![centred(&title), centred(&format!("version {}", env!("CARGO_PKG_VERSION"))), centred("")];
+        // the hints stay aligned with each other, centred as a block
+        let padding = (columns - hints_width) / 2;
+        lines.extend(hints.into_iter().map(|hint| (hint, padding)));
+        lines
     }
-    
-    fn move_cursor(&mut self, direction: KeyCode) {
-        self.cursor_controller.move_cursor(direction, &self.editor_rows)
+
+    /// The last frame's time, size and rows and the recent average, over
+    /// the top right corner.
+    fn draw_frame_stats(&mut self) {
+        let text = format!(" {} ", self.frame_stats.overlay());
+        let text = unicode::truncate_to_width(&text, self.win_size.0);
+        let column = self.win_size.0 - unicode::str_width(text);
+        queue!(
+            self.editor_contents,
+            cursor::MoveTo(column as u16, 0),
+            style::SetAttribute(style::Attribute::Reverse)
+        )
+        .unwrap();
+        self.editor_contents.push_str(text);
+        queue!(self.editor_contents, style::SetAttribute(style::Attribute::Reset)).unwrap();
     }
 
-    fn clear_screen() -> crossterm::Result<()> {
-        execute!(stdout(), terminal::Clear(ClearType::All))?;
-        execute!(stdout(), cursor::MoveTo(0, 0))
+    fn toggle_frame_stats(&mut self) {
+        self.show_frame_stats = !self.show_frame_stats;
+        self.status_message.set_message(self.frame_stats.totals());
     }
 
-    fn draw_rows(&mut self) {
+    /// Draws the picker over the bottom of the text area. Returns where the
+    /// cursor goes: the end of its header line.
+    fn draw_picker(&mut self) -> Option<(usize, usize)> {
+        let (picker, action) = self.picker.as_ref()?;
+        // the help gets the whole screen; other lists leave the text visible
+        let room = match action {
+            PickerAction::Help => self.win_size.1,
+            _ => cmp::min(cmp::max(self.win_size.1 / 2, 2), self.win_size.1),
+        };
+        let height = cmp::min(picker.height(), room);
+        let top = self.win_size.1 - height;
+        let lines = picker.lines(height, self.win_size.0);
+        let cursor_x = cmp::min(unicode::str_width(&lines[0].0), self.win_size.0 - 1);
+        for (i, (line, selected)) in lines.into_iter().enumerate() {
+            queue!(self.editor_contents, cursor::MoveTo(0, (top + i) as u16)).unwrap();
+            if i == 0 || selected {
+                queue!(self.editor_contents, style::SetAttribute(style::Attribute::Reverse)).unwrap();
+            }
+            self.editor_contents.push_str(&line);
+            if i == 0 {
+                (unicode::str_width(&line)..self.win_size.0).for_each(|_| self.editor_contents.push(' '));
+            }
+            queue!(
+                self.editor_contents,
+                style::SetAttribute(style::Attribute::NoReverse),
+                terminal::Clear(ClearType::UntilNewLine)
+            ).unwrap();
+        }
+        Some((cursor_x, top))
+    }
+
+    fn draw_hex_rows(&mut self, hex_view: &HexView) {
         let screen_row = self.win_size.1;
         let screen_column = self.win_size.0;
         for i in 0..screen_row {
             let file_row = i + self.cursor_controller.row_offset;
-
-            if file_row >= self.editor_rows.number_of_rows() {
-                if self.editor_rows.number_of_rows() == 0 && i == screen_row / 3 {
-                    let mut welcome = format!("Rust Text Editor");
-
-                    if welcome.len()> screen_column {
-                        welcome.truncate(screen_column)
-                    }
-                    
-                    let mut padding = (screen_column - welcome.len()) / 2;
-
-                    if padding != 0 {
-                        self.editor_contents.push('~');
-                        padding -= 1
-                    }
-                    (0..padding).for_each(|_| self.editor_contents.push(' '));
-
-                    self.editor_contents.push_str(&welcome);
-                } else {
-                    self.editor_contents.push('~');
-                }
+            if file_row >= hex_view.number_of_rows() {
+                self.editor_contents.push('~');
             } else {
-                let row = self.editor_rows.get_row(file_row);
-                let column_offset = self.cursor_controller.column_offset;
-                let len = cmp::min(row.len().saturating_sub(column_offset), screen_column);
-                let start = if len == 0 { 0 } else { column_offset };
-                self.editor_contents.push_str(&row[start..start+len]);
+                let row = hex_view.render_row(file_row);
+                let start = cmp::min(self.cursor_controller.column_offset, row.len());
+                let len = cmp::min(row.len() - start, screen_column);
+                self.editor_contents.push_str(&row[start..start + len]);
             }
             queue!(
                 self.editor_contents,
                 terminal::Clear(ClearType::UntilNewLine)
             ).unwrap();
-
-            if i < screen_row - 1 {
-                self.editor_contents.push_str("\r\n");
-            }
+            self.editor_contents.push_str("\r\n");
         }
     }
 
     fn refresh_screen(&mut self) -> crossterm::Result<()> {
-        self.cursor_controller.scroll();
+        let start = Instant::now();
         queue!(self.editor_contents, cursor::Hide, cursor::MoveTo(0, 0))? ;
-        self.draw_rows();
+        self.poll_watch();
+        self.poll_loader();
+        self.update_window_size();
+        if self.is_too_small() {
+            return self.draw_too_small();
+        }
+        log::trace!("redraw");
+        if CleanUp::use_title() && self.window_title() != self.shown_title {
+            self.set_title()?;
+        }
+        let offsets = (self.cursor_controller.row_offset, self.cursor_controller.column_offset);
+        if let Some(diff_view) = self.diff_view.take() {
+            // Left and Right move column_offset itself, so only the rows scroll
+            let (column_offset, cursor_y) = (self.cursor_controller.column_offset, self.cursor_controller.cursor_y);
+            self.cursor_controller.screen_column = self.win_size.0;
+            self.cursor_controller.scroll_to(column_offset, cursor_y);
+            self.draw_diff_rows(&diff_view);
+            self.diff_view = Some(diff_view);
+        } else if let Some(hex_view) = self.hex_view.take() {
+            let render_x = HexView::render_x(self.cursor_controller.cursor_x);
+            let render_y = self.cursor_controller.cursor_y;
+            self.cursor_controller.scroll_to(render_x, render_y);
+            self.draw_hex_rows(&hex_view);
+            self.hex_view = Some(hex_view);
+        } else {
+            self.git_gutter.poll();
+            self.cursor_controller.screen_column = self.text_width();
+            self.cursor_controller.scroll(&self.editor_rows);
+            self.draw_rows();
+        }
+        if offsets != (self.cursor_controller.row_offset, self.cursor_controller.column_offset) {
+            log::trace!(
+                "scrolled to row {}, column {}",
+                self.cursor_controller.row_offset,
+                self.cursor_controller.column_offset
+            );
+        }
+        self.draw_status_bar();
+        self.draw_message_bar();
+        let picker_cursor = self.draw_picker();
+        // measured before the overlay so it doesn't count itself
+        let (built, bytes) = (start.elapsed(), self.editor_contents.len());
+        if self.show_frame_stats {
+            self.draw_frame_stats();
+        }
 
-        let cursor_x = self.cursor_controller.cursor_x - self.cursor_controller.column_offset;
-        let cursor_y = self.cursor_controller.cursor_y - self.cursor_controller.row_offset;
+        // a block over the character in the text, an underscore where
+        // something is being typed
+        let (cursor_x, cursor_y, shape) = match (picker_cursor, &self.prompt) {
+            (Some((cursor_x, cursor_y)), _) => (cursor_x, cursor_y, cursor::CursorShape::UnderScore),
+            (None, Some(prompt)) => (
+                cmp::min(unicode::str_width(&prompt.label) + unicode::str_width(&prompt.input), self.win_size.0 - 1),
+                self.win_size.1 + self.reserved_rows() - 1,
+                cursor::CursorShape::UnderScore,
+            ),
+            (None, None) => (
+                self.cursor_controller.render_x - self.cursor_controller.column_offset + self.gutter_width(),
+                self.cursor_controller.render_y - self.cursor_controller.row_offset,
+                cursor::CursorShape::Block,
+            ),
+        };
+        if CleanUp::use_cursor_shape() && self.cursor_shape != Some(shape) {
+            queue!(self.editor_contents, cursor::SetCursorShape(shape))?;
+            self.cursor_shape = Some(shape);
+        }
 
         queue!(self.editor_contents, cursor::MoveTo(cursor_x as u16, cursor_y as u16), cursor::Show)?;
-        self.editor_contents.flush()
+        let flushing = Instant::now();
+        self.editor_contents.flush()?;
+        self.frame_stats.record(built + flushing.elapsed(), bytes, self.win_size.1 + self.reserved_rows());
+        Ok(())
     }
 }
 
@@ -181,6 +2260,11 @@ impl EditorContents {
     fn push_str(&mut self, string: &str) {
         self.content.push_str(string)
     }
+
+    /// Bytes queued since the last flush.
+    fn len(&self) -> usize {
+        self.content.len()
+    }
 }
 
 impl io::Write for EditorContents {
@@ -195,6 +2279,12 @@ impl io::Write for EditorContents {
     }
 
     fn flush(&mut self) -> io::Result<()> {
+        // tests look at `content` before it's flushed, and mustn't draw on
+        // the terminal running them
+        if cfg!(test) {
+            self.content.clear();
+            return Ok(());
+        }
         let out = write!(stdout(), "{}", self.content);
         stdout().flush()?;
         self.content.clear();
@@ -205,10 +2295,16 @@ impl io::Write for EditorContents {
 struct CursorController {
     cursor_x: usize,
     cursor_y: usize,
+    render_x: usize,
+    render_y: usize,
     screen_column: usize,
     screen_row: usize,
     row_offset: usize,
-    column_offset: usize
+    column_offset: usize,
+    /// The render column vertical moves aim for, and the position it was
+    /// last reached at. Once the cursor is anywhere else it has moved
+    /// sideways, so the goal is taken from wherever it is.
+    goal: Option<(usize, (usize, usize))>,
 }
 
 impl CursorController {
@@ -216,30 +2312,48 @@ impl CursorController {
         Self {
             cursor_x: 0,
             cursor_y: 0,
+            render_x: 0,
+            render_y: 0,
             screen_column: win_size.0,
             screen_row: win_size.1,
             row_offset: 0,
             column_offset: 0,
+            goal: None,
         }
     }
 
+    /// Moves to `row`, as close to the goal column as its length allows.
+    fn move_to_row(&mut self, row: usize, editor_rows: &EditorRows) {
+        let number_of_rows = editor_rows.number_of_rows();
+        let goal = match self.goal {
+            Some((goal, position)) if position == (self.cursor_x, self.cursor_y) => goal,
+            _ if self.cursor_y < number_of_rows => editor_rows.get_row(self.cursor_y).render_x(self.cursor_x),
+            _ => 0,
+        };
+        self.cursor_y = row;
+        self.cursor_x = if row < number_of_rows { editor_rows.get_row(row).index_at_column(goal) } else { 0 };
+        self.goal = Some((goal, (self.cursor_x, self.cursor_y)));
+    }
+
     fn move_cursor(&mut self, direction: KeyCode, editor_rows: &EditorRows) {
         let number_of_rows = editor_rows.number_of_rows();
         match direction {
             KeyCode::Up => {
-                self.cursor_y = self.cursor_y.saturating_sub(1);
+                if self.cursor_y > 0 {
+                    self.move_to_row(editor_rows.previous_visible_row(self.cursor_y), editor_rows);
+                }
             }
             KeyCode::Left => {
                 if self.cursor_x != 0 {
                     self.cursor_x -= 1;
                 } else  if self.cursor_y > 0 {
-                    self.cursor_y -= 1;
+                    self.cursor_y = editor_rows.previous_visible_row(self.cursor_y);
                     self.cursor_x = editor_rows.get_row(self.cursor_y).len()
                 }
             }
             KeyCode::Down => {
                 if self.cursor_y < number_of_rows {
-                    self.cursor_y += 1;
+                    self.move_to_row(editor_rows.next_visible_row(self.cursor_y), editor_rows);
                 }
             }
             KeyCode::Right => {
@@ -247,19 +2361,23 @@ impl CursorController {
                     match self.cursor_x.cmp(&editor_rows.get_row(self.cursor_y).len()) {
                         cmp::Ordering::Less => self.cursor_x += 1,
                         cmp::Ordering::Equal => {
-                            self.cursor_y += 1;
+                            self.cursor_y = editor_rows.next_visible_row(self.cursor_y);
                             self.cursor_x = 0
                         }
                         _ => {}
                         
                     }
                 }
-                if self.cursor_y < number_of_rows && self.cursor_x < editor_rows.get_row(self.cursor_y).len() {
-                    self.cursor_x += 1;
-                }
             }
             KeyCode::End => self.cursor_x = self.screen_column - 1,
-            KeyCode::Home => self.cursor_x = 0,
+            KeyCode::Home => {
+                let first_non_blank = if self.cursor_y < number_of_rows {
+                    editor_rows.get_row(self.cursor_y).first_non_blank()
+                } else {
+                    0
+                };
+                self.cursor_x = if self.cursor_x == first_non_blank { 0 } else { first_non_blank }
+            }
             _ => unimplemented!()
         }
 
@@ -272,17 +2390,27 @@ impl CursorController {
         self.cursor_x = cmp::min(self.cursor_x, row_len);
     }
 
-    fn scroll(&mut self) {
-        self.row_offset = cmp::min(self.row_offset, self.cursor_y);
+    fn scroll(&mut self, editor_rows: &EditorRows) {
+        let mut render_x = 0;
+        if self.cursor_y < editor_rows.number_of_rows() {
+            render_x = editor_rows.get_row(self.cursor_y).render_x(self.cursor_x);
+        }
+        self.scroll_to(render_x, editor_rows.visible_row(self.cursor_y))
+    }
+
+    fn scroll_to(&mut self, render_x: usize, render_y: usize) {
+        self.render_x = render_x;
+        self.render_y = render_y;
+        self.row_offset = cmp::min(self.row_offset, self.render_y);
         
-        if self.cursor_y >= self.row_offset + self.screen_row {
-            self.row_offset = self.cursor_y - self.screen_row + 1;
+        if self.render_y >= self.row_offset + self.screen_row {
+            self.row_offset = self.render_y - self.screen_row + 1;
         }
 
-        self.column_offset = cmp::min(self.column_offset, self.cursor_x);
+        self.column_offset = cmp::min(self.column_offset, self.render_x);
 
-        if self.cursor_x >= self.column_offset + self.screen_column {
-            self.column_offset = self.cursor_x - self.screen_column + 1;
+        if self.render_x >= self.column_offset + self.screen_column {
+            self.column_offset = self.render_x - self.screen_column + 1;
         }
     }
 }
@@ -290,55 +2418,675 @@ impl CursorController {
 
 struct CleanUp;
 
+impl CleanUp {
+    fn use_alternate_screen() -> bool {
+        env::var_os("RTE_NO_ALT_SCREEN").is_none()
+    }
+
+    fn use_title() -> bool {
+        env::var_os("RTE_NO_TITLE").is_none()
+    }
+
+    fn use_cursor_shape() -> bool {
+        env::var_os("RTE_NO_CURSOR_SHAPE").is_none()
+    }
+
+    fn enter() -> crossterm::Result<Self> {
+        Self::set_up_terminal()?;
+        Ok(Self)
+    }
+
+    fn set_up_terminal() -> crossterm::Result<()> {
+        terminal::enable_raw_mode()?;
+        if Self::use_alternate_screen() {
+            execute!(stdout(), terminal::EnterAlternateScreen)?;
+        }
+        if Self::use_title() {
+            // save the current title on the terminal's title stack (XTWINOPS)
+            write!(stdout(), "\x1b[22;0t")?;
+        }
+        Ok(())
+    }
+
+    fn restore_terminal() -> crossterm::Result<()> {
+        terminal::disable_raw_mode()?;
+        if Self::use_title() {
+            write!(stdout(), "\x1b[23;0t")?;
+        }
+        if Self::use_cursor_shape() {
+            // back to the terminal's own default shape
+            write!(stdout(), "\x1b[0 q")?;
+        }
+        if Self::use_alternate_screen() {
+            execute!(stdout(), terminal::LeaveAlternateScreen)
+        } else {
+            Output::clear_screen()
+        }
+    }
+}
+
 impl Drop for CleanUp {
     fn drop(&mut self) {
-        terminal::disable_raw_mode().expect("Could not disable raw mode");
-        Output::clear_screen().expect("Error");
+        Self::restore_terminal().expect("Could not restore the terminal");
     }
 }
 
-struct EditorRows {
-    row_contents: Vec<Box<str>>,
+struct Row {
+    row_content: Box<str>,
+    len: usize,
+    tab_stop: usize,
+    /// (byte index, render column) of every CHECKPOINT_INTERVAL-th grapheme
+    /// after the first, so positions on very long rows can be found without
+    /// walking the row from its start.
+    checkpoints: Vec<(usize, usize)>,
 }
 
-impl EditorRows {
-    fn new() -> Self {
-        let mut arg = env::args();
+impl Row {
+    fn new(row_content: Box<str>, tab_stop: usize) -> Self {
+        let mut row = Self {
+            row_content,
+            len: 0,
+            tab_stop,
+            checkpoints: Vec::new(),
+        };
+        let (mut byte_index, mut render_x) = (0, 0);
+        for grapheme in unicode::graphemes(&row.row_content) {
+            row.len += 1;
+            byte_index += grapheme.len();
+            render_x += row.grapheme_width(grapheme, render_x);
+            if row.len.is_multiple_of(CHECKPOINT_INTERVAL) {
+                row.checkpoints.push((byte_index, render_x));
+            }
+        }
+        row
+    }
 
-        match arg.nth(1) {
-            None => Self {
-                row_contents: Vec::new(),
-            },
-            Some(file) => Self::from_file(file.as_ref()),
-        } 
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn checkpoint(&self, index: usize) -> (usize, usize) {
+        match index {
+            0 => (0, 0),
+            _ => self.checkpoints[index - 1],
+        }
+    }
+
+    /// Byte index and render column of grapheme `at`.
+    fn position(&self, at: usize) -> (usize, usize) {
+        let (mut byte_index, mut render_x) = self.checkpoint(at / CHECKPOINT_INTERVAL);
+        for grapheme in unicode::graphemes(&self.row_content[byte_index..]).take(at % CHECKPOINT_INTERVAL) {
+            byte_index += grapheme.len();
+            render_x += self.grapheme_width(grapheme, render_x);
+        }
+        (byte_index, render_x)
+    }
+
+    /// Byte index and render column of a grapheme boundary at or before
+    /// render column `column`, to start drawing from.
+    fn position_before(&self, column: usize) -> (usize, usize) {
+        let index = self.checkpoints.partition_point(|&(_, render_x)| render_x <= column);
+        self.checkpoint(index)
+    }
+
+    /// Index of the grapheme containing byte `byte`, or the row's length
+    /// past its end.
+    fn grapheme_at_byte(&self, byte: usize) -> usize {
+        let index = self.checkpoints.partition_point(|&(byte_index, _)| byte_index <= byte);
+        let (mut byte_index, _) = self.checkpoint(index);
+        let mut at = index * CHECKPOINT_INTERVAL;
+        for grapheme in unicode::graphemes(&self.row_content[byte_index..]) {
+            if byte_index + grapheme.len() > byte {
+                break;
+            }
+            byte_index += grapheme.len();
+            at += 1;
+        }
+        at
+    }
+
+    fn grapheme(&self, at: usize) -> Option<&str> {
+        let (byte_index, _) = self.position(at);
+        unicode::graphemes(&self.row_content[byte_index..]).next()
+    }
+
+    fn grapheme_width(&self, grapheme: &str, render_x: usize) -> usize {
+        if grapheme == "\t" {
+            self.tab_stop - render_x % self.tab_stop
+        } else {
+            unicode::width(grapheme)
+        }
+    }
+
+    /// The identifier around grapheme `at`, if it's on one.
+    fn word_at(&self, at: usize) -> Option<&str> {
+        let is_word = |ch: char| ch.is_alphanumeric() || ch == '_';
+        let (byte_index, _) = self.position(at);
+        let content = &*self.row_content;
+        let start = content[..byte_index]
+            .char_indices()
+            .rev()
+            .take_while(|&(_, ch)| is_word(ch))
+            .last()
+            .map_or(byte_index, |(start, _)| start);
+        let end = content[byte_index..].find(|ch: char| !is_word(ch)).map_or(content.len(), |end| byte_index + end);
+        (start < end).then(|| &content[start..end])
+    }
+
+    /// Index of the grapheme covering render column `column`, or the row's
+    /// length if it's past the end.
+    fn index_at_column(&self, column: usize) -> usize {
+        let index = self.checkpoints.partition_point(|&(_, render_x)| render_x <= column);
+        let (byte_index, mut render_x) = self.checkpoint(index);
+        let mut at = index * CHECKPOINT_INTERVAL;
+        for grapheme in unicode::graphemes(&self.row_content[byte_index..]) {
+            render_x += self.grapheme_width(grapheme, render_x);
+            if render_x > column {
+                break;
+            }
+            at += 1;
+        }
+        at
+    }
+
+    /// Grapheme index just past the row's leading whitespace.
+    fn first_non_blank(&self) -> usize {
+        unicode::graphemes(&self.row_content)
+            .take_while(|&grapheme| grapheme == " " || grapheme == "\t")
+            .count()
+    }
+
+    fn indentation(&self) -> Option<usize> {
+        let mut indentation = 0;
+        for ch in self.row_content.chars() {
+            match ch {
+                ' ' => indentation += 1,
+                '\t' => indentation += self.tab_stop - indentation % self.tab_stop,
+                _ => return Some(indentation),
+            }
+        }
+        None
     }
-    
-    fn from_file(file: &Path) -> Self {
-        let file_contents = fs::read_to_string(file).expect("Unable to read file");
 
+    fn render_x(&self, at: usize) -> usize {
+        self.position(at).1
+    }
+}
+
+struct Fold {
+    start: usize,
+    end: usize,
+}
+
+impl Fold {
+    fn hidden_rows(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+struct EditorRows {
+    row_contents: Vec<Row>,
+    folds: Vec<Fold>,
+    tab_stop: usize,
+    /// As the loader found it on the first line.
+    line_ending: LineEnding,
+}
+
+impl EditorRows {
+    fn new(tab_stop: usize) -> Self {
         Self {
-            row_contents: file_contents.lines().map(|it| it.into()).collect(),
+            row_contents: Vec::new(),
+            folds: Vec::new(),
+            tab_stop,
+            line_ending: LineEnding::Lf,
         }
     }
+    
+    fn extend(&mut self, lines: Vec<String>) {
+        let tab_stop = self.tab_stop;
+        self.row_contents
+            .extend(lines.into_iter().map(|it| Row::new(it.into_boxed_str(), tab_stop)))
+    }
 
     fn number_of_rows(&self) -> usize {
         self.row_contents.len()
     }
 
-    fn get_row(&self, at:usize) -> &str {
+    /// Byte offset of grapheme `at` of row `y` in the file, counting the
+    /// file's line ending after each row.
+    fn byte_offset(&self, at: usize, y: usize) -> usize {
+        let newline = self.line_ending.len();
+        let before: usize = self.row_contents[..y].iter().map(|row| row.row_content.len() + newline).sum();
+        match self.row_contents.get(y) {
+            Some(row) => before + row.position(at).0,
+            None => before,
+        }
+    }
+
+    /// Row and grapheme containing file byte `offset`, clamped to the end
+    /// of the last row.
+    fn position_of_byte(&self, offset: usize) -> (usize, usize) {
+        let mut start = 0;
+        for (y, row) in self.row_contents.iter().enumerate() {
+            // an offset inside the line ending is taken as its end
+            if offset < start + row.row_content.len() + self.line_ending.len() {
+                return (row.grapheme_at_byte(cmp::min(offset - start, row.row_content.len())), y);
+            }
+            start += row.row_content.len() + self.line_ending.len();
+        }
+        match self.row_contents.last() {
+            Some(row) => (row.len(), self.row_contents.len() - 1),
+            None => (0, 0),
+        }
+    }
+
+    fn set_tab_stop(&mut self, tab_stop: usize) {
+        if tab_stop != self.tab_stop {
+            self.tab_stop = tab_stop;
+            for row in &mut self.row_contents {
+                *row = Row::new(std::mem::take(&mut row.row_content), tab_stop);
+            }
+        }
+    }
+
+    fn get_row(&self, at:usize) -> &Row {
         &self.row_contents[at]
     }
+
+    /// Row, grapheme column, and character of every invisible or
+    /// confusable character in the buffer.
+    fn invisible_characters(&self) -> Vec<(usize, usize, char)> {
+        let mut found = Vec::new();
+        for (y, row) in self.row_contents.iter().enumerate() {
+            for (x, grapheme) in unicode::graphemes(&row.row_content).enumerate() {
+                for ch in grapheme.chars().filter(|&ch| unicode::invisible(ch).is_some()) {
+                    found.push((y, x, ch));
+                }
+            }
+        }
+        found
+    }
+
+    fn snapshot(&self) -> Vec<String> {
+        self.row_contents.iter().map(|row| row.row_content.to_string()).collect()
+    }
+
+    fn reveal(&mut self, at: usize) {
+        self.folds.retain(|fold| at <= fold.start || at > fold.end)
+    }
+
+    fn fold_at(&self, at: usize) -> Option<&Fold> {
+        self.folds.iter().find(|fold| fold.start == at)
+    }
+
+    fn number_of_visible_rows(&self) -> usize {
+        self.visible_row(self.number_of_rows())
+    }
+
+    /// Screen-order index of file row `at`. Rows hidden inside a fold map
+    /// to the fold's header row.
+    fn visible_row(&self, at: usize) -> usize {
+        let mut visible_row = at;
+        for fold in &self.folds {
+            if fold.end < at {
+                visible_row -= fold.hidden_rows();
+            } else {
+                if fold.start < at {
+                    visible_row -= at - fold.start;
+                }
+                break;
+            }
+        }
+        visible_row
+    }
+
+    fn file_row(&self, visible_row: usize) -> usize {
+        let mut file_row = visible_row;
+        for fold in &self.folds {
+            if fold.start >= file_row {
+                break;
+            }
+            file_row += fold.hidden_rows();
+        }
+        file_row
+    }
+
+    fn next_visible_row(&self, at: usize) -> usize {
+        self.fold_at(at).map_or(at, |fold| fold.end) + 1
+    }
+
+    fn previous_visible_row(&self, at: usize) -> usize {
+        self.file_row(self.visible_row(at) - 1)
+    }
+
+    /// Folds the rows after `at` that are indented deeper than it, and
+    /// returns how many rows were hidden.
+    fn fold_block(&mut self, at: usize) -> Option<usize> {
+        let indentation = self.row_contents.get(at)?.indentation()?;
+        let mut end = at;
+        for (i, row) in self.row_contents.iter().enumerate().skip(at + 1) {
+            match row.indentation() {
+                None => continue,
+                Some(row_indentation) if row_indentation > indentation => end = i,
+                Some(_) => break,
+            }
+        }
+        if end == at {
+            return None;
+        }
+        self.folds.retain(|fold| fold.start < at || fold.start > end);
+        let index = self.folds.iter().position(|fold| fold.start > at).unwrap_or(self.folds.len());
+        self.folds.insert(index, Fold { start: at, end });
+        Some(end - at)
+    }
+
+    fn unfold(&mut self, at: usize) -> bool {
+        let before = self.folds.len();
+        self.folds.retain(|fold| fold.start != at);
+        self.folds.len() != before
+    }
+
+    fn fold_all(&mut self) -> usize {
+        self.folds.clear();
+        let mut folds = 0;
+        let mut at = 0;
+        while at < self.number_of_rows() {
+            if self.row_contents[at].indentation() == Some(0) {
+                if let Some(hidden_rows) = self.fold_block(at) {
+                    folds += 1;
+                    at += hidden_rows;
+                }
+            }
+            at += 1;
+        }
+        folds
+    }
+
+    fn unfold_all(&mut self) {
+        self.folds.clear()
+    }
 }
 
 
 fn main() -> crossterm::Result<()> {
-    let _clean_up = CleanUp;
+    let args = match args::parse(env::args().skip(1)) {
+        Ok(Command::Run(args)) => args,
+        Ok(Command::Help) => {
+            println!("{}", args::USAGE);
+            return Ok(());
+        }
+        Ok(Command::Version) => {
+            println!("rte {}", env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        }
+        Err(error) => {
+            eprintln!("rte: {}\n{}", error, args::USAGE);
+            std::process::exit(2);
+        }
+    };
+    logging::init()?;
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        log::error!("{}", info);
+        let _ = CleanUp::restore_terminal();
+        default_hook(info)
+    }));
 
-    terminal::enable_raw_mode()?;
+    let clean_up = CleanUp::enter()?;
+    // crossterm only notices the SIGWINCH the handler raises once its event
+    // source, created by the first poll, is listening for it
+    event::poll(Duration::ZERO)?;
+    signals::install();
 
-    let mut editor = Editor::new();
+    let mut editor = Editor::new(args);
+    if CleanUp::use_title() {
+        editor.output.set_title()?;
+    }
 
-    while editor.run() ? {}
+    loop {
+        let running = editor.run();
+        // checked first: after SIGHUP, drawing fails on the closed terminal
+        if let Some(signal) = signals::received() {
+            exit_on_signal(&editor, clean_up, signal);
+        }
+        if !running? {
+            break;
+        }
+    }
+    editor.output.save_session();
 
     Ok(())
+}
+
+/// Quits for SIGTERM or SIGHUP. The terminal may already be gone, so
+/// failing to restore it is no reason to panic, as dropping CleanUp would.
+fn exit_on_signal(editor: &Editor, clean_up: CleanUp, signal: i32) -> ! {
+    log::info!("quitting on signal {}", signal);
+    editor.output.save_session();
+    std::mem::forget(clean_up);
+    let _ = CleanUp::restore_terminal();
+    std::process::exit(128 + signal)
 } 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(text: &str) -> Row {
+        Row::new(text.into(), TAB_STOP)
+    }
+
+    /// What draw_row_segment puts on screen, and the columns it reports.
+    fn draw(row: &Row, column_offset: usize, width: usize) -> (String, usize) {
+        let mut editor_contents = EditorContents::new();
+        let used = Output::draw_row_segment(&mut editor_contents, row, column_offset, width, &[]);
+        (editor_contents.content, used)
+    }
+
+    #[test]
+    fn columns_on_mixed_ascii_and_cjk() {
+        let row = row("ab\u{4e2d}\u{6587}c");
+        let columns: Vec<usize> = (0..=row.len()).map(|at| row.render_x(at)).collect();
+        assert_eq!(columns, vec![0, 1, 2, 4, 6, 7]);
+        // both halves of a wide character belong to it
+        assert_eq!(row.index_at_column(2), 2);
+        assert_eq!(row.index_at_column(3), 2);
+        assert_eq!(row.index_at_column(4), 3);
+        assert_eq!(row.index_at_column(6), 4);
+        assert_eq!(row.index_at_column(7), 5);
+    }
+
+    #[test]
+    fn wide_character_cut_at_the_right_edge_is_padded() {
+        let row = row("a\u{4e2d}b");
+        assert_eq!(draw(&row, 0, 2), (String::from("a "), 2));
+        assert_eq!(draw(&row, 0, 3), (String::from("a\u{4e2d}"), 3));
+    }
+
+    #[test]
+    fn wide_character_cut_at_the_left_edge_is_padded() {
+        let row = row("a\u{4e2d}b");
+        // scrolled into the middle of the wide character
+        assert_eq!(draw(&row, 2, 5), (String::from(" b"), 2));
+        assert_eq!(draw(&row, 1, 5), (String::from("\u{4e2d}b"), 3));
+    }
+
+    #[test]
+    fn truncating_never_splits_a_wide_character() {
+        assert_eq!(unicode::truncate_to_width("ab\u{4e2d}c", 3), "ab");
+        assert_eq!(unicode::truncate_to_width("ab\u{4e2d}c", 4), "ab\u{4e2d}");
+        assert_eq!(unicode::str_width("ab\u{4e2d}c"), 5);
+    }
+
+    /// Events that arrive `delay` after the previous one was read, and how
+    /// many times the editor woke up to wait for one.
+    struct Script(std::collections::VecDeque<(Duration, Event)>, usize);
+
+    impl EventSource for Script {
+        fn poll(&mut self, timeout: Duration) -> crossterm::Result<bool> {
+            self.1 += 1;
+            match self.0.front_mut() {
+                Some((delay, _)) if *delay <= timeout => Ok(true),
+                Some((delay, _)) => {
+                    *delay -= timeout;
+                    Ok(false)
+                }
+                None => Ok(false),
+            }
+        }
+
+        fn read(&mut self) -> crossterm::Result<Event> {
+            self.1 += 1;
+            Ok(self.0.pop_front().expect("read past the end of the script").1)
+        }
+    }
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Event {
+        Event::Key(KeyEvent::new(code, modifiers))
+    }
+
+    /// Every key read from `events`, with ESC_ALT on.
+    fn read_all(events: Vec<(Duration, Event)>) -> Vec<Event> {
+        let mut reader = Reader::with_source(Script(events.into(), 0), true);
+        let mut read = Vec::new();
+        while let Some(event) = reader.read_event(Some(Duration::from_secs(1))).unwrap() {
+            read.push(event);
+        }
+        read
+    }
+
+    #[test]
+    fn esc_right_before_a_character_is_alt() {
+        let events = vec![
+            (Duration::ZERO, key(KeyCode::Esc, KeyModifiers::NONE)),
+            (Duration::from_millis(5), key(KeyCode::Char('x'), KeyModifiers::NONE)),
+        ];
+        assert_eq!(read_all(events), vec![key(KeyCode::Char('x'), KeyModifiers::ALT)]);
+    }
+
+    #[test]
+    fn esc_on_its_own_stays_esc() {
+        let esc = key(KeyCode::Esc, KeyModifiers::NONE);
+        let x = key(KeyCode::Char('x'), KeyModifiers::NONE);
+        // the x comes too late to pair up
+        let events = vec![(Duration::ZERO, esc), (ESC_ALT_DELAY * 3, x)];
+        assert_eq!(read_all(events), vec![esc, x]);
+        // nor does Esc pair with something that isn't a plain character
+        let up = key(KeyCode::Up, KeyModifiers::NONE);
+        let events = vec![(Duration::ZERO, esc), (Duration::ZERO, up)];
+        assert_eq!(read_all(events), vec![esc, up]);
+    }
+
+    #[test]
+    fn alt_from_the_terminal_passes_through() {
+        let alt_x = key(KeyCode::Char('x'), KeyModifiers::ALT);
+        let events = vec![(Duration::ZERO, alt_x), (Duration::ZERO, alt_x)];
+        assert_eq!(read_all(events), vec![alt_x, alt_x]);
+    }
+
+    #[test]
+    fn outline_from_before_a_reload_is_out_of_date() {
+        let mut output = Output::new(Args::default());
+        let items = vec![String::from("fn main")];
+        output.picker = Some((Picker::new(String::from("Outline"), items), PickerAction::Outline(vec![40])));
+        output.picker_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(output.picker.is_none());
+        assert_eq!(output.status_message.message().map(String::as_str), Some("The outline is out of date"));
+    }
+
+    #[test]
+    fn session_files_that_vanished() {
+        let path = env::temp_dir().join(format!("rte-restore-{}", std::process::id()));
+        let session = Session {
+            file: Some(String::from("gone/main.rs")),
+            cursor: (0, 3),
+            row_offset: 0,
+            bookmarks: vec![(String::from("Cargo.toml"), vec![1]), (String::from("gone.rs"), vec![2])],
+        };
+        session.save(&path).unwrap();
+        let mut output = Output::new(Args::default());
+        output.session = Some(path);
+        output.restore_session();
+        assert!(output.file_name.is_none() && output.pending_jump.is_none());
+        assert_eq!(output.bookmarks.keys().collect::<Vec<_>>(), ["Cargo.toml"]);
+        assert_eq!(
+            output.status_message.message().map(String::as_str),
+            Some("Gone since the session was saved: gone.rs, gone/main.rs")
+        );
+    }
+
+    #[test]
+    fn idle_minutes_draw_nothing() {
+        let minute = Duration::from_secs(60);
+        let events = vec![(minute, Event::Resize(80, 24)), (minute, key(KeyCode::Char('q'), KeyModifiers::CONTROL))];
+        let mut editor = Editor {
+            reader: Reader::with_source(Script(events.into(), 0), false),
+            output: Output::new(Args::default()),
+            prefix: None,
+        };
+        // a frame on starting and one for the resize, each after a single
+        // wait, and none in the minutes between
+        assert!(editor.run().unwrap());
+        assert!(!editor.run().unwrap());
+        assert_eq!(editor.output.frame_stats.frames, 2);
+        assert_eq!(editor.reader.source.1, 2);
+    }
+
+    #[test]
+    fn status_message_deadline() {
+        let mut status_message = StatusMessage::new();
+        assert_eq!(status_message.time_left(), None);
+        status_message.set_message(String::from("hello"));
+        let left = status_message.time_left().unwrap();
+        assert!(left > Duration::from_secs(4) && left <= Duration::from_secs(5));
+        status_message.set_time = Some(Instant::now() - Duration::from_secs(6));
+        assert_eq!(status_message.time_left(), Some(Duration::ZERO));
+        assert!(status_message.message().is_none());
+        assert_eq!(status_message.time_left(), None);
+    }
+
+    #[test]
+    fn lookups_on_a_megabyte_row_start_from_a_checkpoint() {
+        // ASCII with a wide character now and then, so bytes, graphemes and
+        // columns all differ
+        let text = "abcdefg\u{4e2d}".repeat((1024 * 1024_usize).div_ceil(10));
+        assert!(text.len() >= 1024 * 1024);
+        let row = row(&text);
+        let len = row.len();
+        assert_eq!(row.checkpoints.len(), len / CHECKPOINT_INTERVAL);
+        // what a frame asks of the cursor's row, at points all along it
+        for at in (0..24).map(|at| (len - 1) * at / 23) {
+            let (byte, column) = row.position(at);
+            assert_eq!(row.index_at_column(column), at);
+            assert_eq!(row.grapheme_at_byte(byte), at);
+            // drawing starts at most a checkpoint interval before the cursor
+            let (start_byte, start_column) = row.position_before(column);
+            assert!(start_byte <= byte && start_column <= column);
+            let walked = unicode::graphemes(&text[start_byte..byte]).count();
+            assert!(walked <= CHECKPOINT_INTERVAL, "{} graphemes walked to grapheme {}", walked, at);
+        }
+        // and the checkpoints are where a walk from the start gets to
+        let at = 3 * CHECKPOINT_INTERVAL + 5;
+        let (byte, column) = row.position(at);
+        let before: Vec<&str> = unicode::graphemes(&text).take(at).collect();
+        assert_eq!(byte, before.iter().map(|grapheme| grapheme.len()).sum::<usize>());
+        assert_eq!(column, before.iter().map(|&grapheme| unicode::width(grapheme)).sum::<usize>());
+    }
+
+    #[test]
+    fn tiny_terminals_get_the_notice() {
+        for (columns, rows) in [(0, 0), (1, 1), (5, 2), (MIN_SIZE.0 - 1, 24), (80, MIN_SIZE.1 - 1)] {
+            assert!(Output::too_small(columns, rows), "{}x{}", columns, rows);
+        }
+        assert!(!Output::too_small(MIN_SIZE.0, MIN_SIZE.1));
+        assert!(!Output::too_small(80, 24));
+    }
+
+    #[test]
+    fn the_notice_fits_the_width() {
+        assert_eq!(Output::too_small_notice(0), "");
+        assert_eq!(Output::too_small_notice(1), "w");
+        assert_eq!(Output::too_small_notice(5), "windo");
+        assert_eq!(Output::too_small_notice(80), "window too small (need 10x3)");
+    }
+}
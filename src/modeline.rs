@@ -0,0 +1,170 @@
+// Vim and Emacs modelines. Only a handful of harmless options are honoured;
+// anything else in a modeline is ignored.
+
+use crate::editorconfig::Property;
+
+/// Vim looks at this many lines at each end of the file.
+pub const MODELINES: usize = 5;
+
+/// Settings from the modelines among `lines`, given as (row, contents).
+/// Emacs only reads its `-*- ... -*-` line from the top of the file.
+pub fn properties(lines: &[(usize, &str)]) -> Vec<Property> {
+    let mut properties: Vec<Property> = Vec::new();
+    for &(at, line) in lines {
+        let mut options = vim(line).unwrap_or_default();
+        if at < 2 {
+            options.extend(emacs(line).unwrap_or_default());
+        }
+        for (name, value) in options {
+            properties.retain(|property| property.name != name);
+            properties.push(Property {
+                name: String::from(name),
+                value,
+                source: format!("modeline, line {}", at + 1),
+            });
+        }
+    }
+    properties
+}
+
+/// `vim: ts=4 et` or `vim: set ts=4 et:`, after whitespace or at the start
+/// of the line. The `set` form must be closed by a `:`.
+fn vim(line: &str) -> Option<Vec<(&'static str, String)>> {
+    let (at, marker) = ["vim:", "Vim:", "vi:", "ex:"]
+        .iter()
+        .filter_map(|marker| {
+            let at = line.match_indices(marker).map(|(at, _)| at).find(|&at| {
+                match line[..at].chars().next_back() {
+                    None => *marker != "ex:",
+                    Some(ch) => ch.is_whitespace(),
+                }
+            })?;
+            Some((at, *marker))
+        })
+        .min()?;
+    let rest = line[at + marker.len()..].trim_start();
+    let options: Vec<&str> = match rest.strip_prefix("set ").or_else(|| rest.strip_prefix("se ")) {
+        Some(set) => set[..set.find(':')?].split_whitespace().collect(),
+        None => rest
+            .split(|ch: char| ch.is_whitespace() || ch == ':')
+            .filter(|option| !option.is_empty())
+            .collect(),
+    };
+    Some(options.into_iter().filter_map(vim_option).collect())
+}
+
+fn vim_option(option: &str) -> Option<(&'static str, String)> {
+    let (name, value) = match option.split_once('=') {
+        Some((name, value)) => (name, Some(value)),
+        None => (option, None),
+    };
+    match (name, value) {
+        ("ts" | "tabstop", Some(value)) => Some(("tabstop", tab_stop(value)?)),
+        ("et" | "expandtab", None) => Some(("expandtab", String::from("true"))),
+        ("noet" | "noexpandtab", None) => Some(("expandtab", String::from("false"))),
+        ("ft" | "filetype", Some(value)) => Some(("filetype", file_type(value)?)),
+        ("ro" | "readonly", None) => Some(("readonly", String::from("true"))),
+        ("noro" | "noreadonly", None) => Some(("readonly", String::from("false"))),
+        _ => None,
+    }
+}
+
+/// `-*- mode: rust; tab-width: 4 -*-`, or just `-*- rust -*-`.
+fn emacs(line: &str) -> Option<Vec<(&'static str, String)>> {
+    let start = line.find("-*-")? + 3;
+    let end = start + line[start..].find("-*-")?;
+    let body = line[start..end].trim();
+    if !body.contains(':') {
+        return Some(file_type(body).map(|value| ("filetype", value)).into_iter().collect());
+    }
+    let mut options = Vec::new();
+    for variable in body.split(';') {
+        let (name, value) = match variable.split_once(':') {
+            Some((name, value)) => (name.trim().to_lowercase(), value.trim()),
+            None => continue,
+        };
+        let option = match (name.as_str(), value) {
+            ("mode", value) => file_type(value).map(|value| ("filetype", value)),
+            ("tab-width", value) => tab_stop(value).map(|value| ("tabstop", value)),
+            ("indent-tabs-mode", "nil") => Some(("expandtab", String::from("true"))),
+            ("indent-tabs-mode", "t") => Some(("expandtab", String::from("false"))),
+            ("buffer-read-only", "t") => Some(("readonly", String::from("true"))),
+            ("buffer-read-only", "nil") => Some(("readonly", String::from("false"))),
+            _ => None,
+        };
+        options.extend(option);
+    }
+    Some(options)
+}
+
+fn tab_stop(value: &str) -> Option<String> {
+    let width: usize = value.parse().ok()?;
+    (1..=32).contains(&width).then(|| width.to_string())
+}
+
+fn file_type(value: &str) -> Option<String> {
+    let valid = !value.is_empty()
+        && value.chars().all(|ch| ch.is_ascii_alphanumeric() || "_-.+".contains(ch));
+    valid.then(|| value.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(found: Option<Vec<(&'static str, String)>>) -> Vec<(&'static str, String)> {
+        found.unwrap_or_default()
+    }
+
+    fn option(name: &'static str, value: &str) -> (&'static str, String) {
+        (name, String::from(value))
+    }
+
+    #[test]
+    fn vim_first_form() {
+        assert_eq!(
+            options(vim("// vim: ts=4 et ft=rust")),
+            vec![option("tabstop", "4"), option("expandtab", "true"), option("filetype", "rust")]
+        );
+        assert_eq!(options(vim("# vi:noet:ts=8")), vec![option("expandtab", "false"), option("tabstop", "8")]);
+    }
+
+    #[test]
+    fn vim_set_form_needs_its_colon() {
+        assert_eq!(options(vim("/* vim: set ts=2 ro: */")), vec![option("tabstop", "2"), option("readonly", "true")]);
+        assert!(vim("/* vim: set ts=2 */").is_none());
+    }
+
+    #[test]
+    fn vim_marker_placement() {
+        // must follow whitespace or start the line; ex: can't start it
+        assert!(vim("novim: ts=4").is_none());
+        assert!(vim("ex: ts=4").is_none());
+        assert_eq!(options(vim(" ex: ts=4")), vec![option("tabstop", "4")]);
+        assert_eq!(options(vim("vim: ts=4")), vec![option("tabstop", "4")]);
+    }
+
+    #[test]
+    fn vim_ignores_what_it_does_not_honour() {
+        assert_eq!(options(vim("vim: ts=99 foldmethod=marker sw=4 ts=3")), vec![option("tabstop", "3")]);
+        assert!(options(vim("vim: ft=$(rm)")).is_empty());
+    }
+
+    #[test]
+    fn emacs_variables() {
+        assert_eq!(
+            options(emacs(";; -*- mode: Python; tab-width: 4; indent-tabs-mode: nil -*-")),
+            vec![option("filetype", "python"), option("tabstop", "4"), option("expandtab", "true")]
+        );
+        assert_eq!(options(emacs("# -*- ruby -*-")), vec![option("filetype", "ruby")]);
+        assert!(emacs("-*- unclosed").is_none());
+    }
+
+    #[test]
+    fn emacs_only_near_the_top_and_later_lines_win() {
+        let found = properties(&[(0, "# -*- tab-width: 4 -*-"), (1, "# vim: ts=2"), (40, "# -*- tab-width: 6 -*-")]);
+        let values: Vec<(&str, &str, &str)> =
+            found.iter().map(|property| (&*property.name, &*property.value, &*property.source)).collect();
+        assert_eq!(values, vec![("tabstop", "2", "modeline, line 2")]);
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Frames the rolling average is taken over.
+const RECENT: usize = 30;
+
+/// How long redraws take and how much they write to the terminal, for
+/// finding out why the editor is slow over a slow link. Always kept, since
+/// it's only a clock reading and some additions per frame.
+pub struct FrameStats {
+    pub frames: u64,
+    total_time: Duration,
+    total_bytes: u64,
+    recent: VecDeque<Duration>,
+    last: Option<(Duration, usize, usize)>,
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        Self {
+            frames: 0,
+            total_time: Duration::ZERO,
+            total_bytes: 0,
+            recent: VecDeque::with_capacity(RECENT),
+            last: None,
+        }
+    }
+
+    pub fn record(&mut self, time: Duration, bytes: usize, rows: usize) {
+        self.frames += 1;
+        self.total_time += time;
+        self.total_bytes += bytes as u64;
+        if self.recent.len() == RECENT {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(time);
+        self.last = Some((time, bytes, rows));
+    }
+
+    /// The last frame and the recent average: `1.8 ms 6.1 KB 23 rows avg 2.0 ms`.
+    pub fn overlay(&self) -> String {
+        let (time, bytes, rows) = match self.last {
+            Some(last) => last,
+            None => return String::from("no frames yet"),
+        };
+        let average = self.recent.iter().sum::<Duration>() / self.recent.len() as u32;
+        format!("{} {} {} rows avg {}", millis(time), size(bytes as u64), rows, millis(average))
+    }
+
+    /// Everything since the editor started.
+    pub fn totals(&self) -> String {
+        let average = self.total_time.checked_div(self.frames as u32).unwrap_or_default();
+        format!(
+            "{} frames, {} drawing, {} average; {} written",
+            self.frames,
+            millis(self.total_time),
+            millis(average),
+            size(self.total_bytes)
+        )
+    }
+}
+
+fn millis(time: Duration) -> String {
+    format!("{:.1} ms", time.as_secs_f64() * 1000.0)
+}
+
+fn size(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1_048_575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
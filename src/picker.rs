@@ -0,0 +1,85 @@
+use crate::unicode;
+use crossterm::event::KeyCode;
+use std::cmp;
+
+pub enum Picked {
+    /// Index of the chosen item among those the picker was made with.
+    Item(usize),
+    Cancelled,
+}
+
+/// A list to choose one item from, drawn over the bottom of the screen.
+/// Typing narrows it down to the items containing what was typed.
+pub struct Picker {
+    pub title: String,
+    items: Vec<String>,
+    pub filter: String,
+    /// Indices of the items that pass the filter.
+    shown: Vec<usize>,
+    selected: usize,
+}
+
+impl Picker {
+    pub fn new(title: String, items: Vec<String>) -> Self {
+        let shown = (0..items.len()).collect();
+        Self {
+            title,
+            items,
+            filter: String::new(),
+            shown,
+            selected: 0,
+        }
+    }
+
+    fn update_filter(&mut self) {
+        let filter = self.filter.to_lowercase();
+        let items = &self.items;
+        self.shown = (0..items.len()).filter(|&at| items[at].to_lowercase().contains(&filter)).collect();
+        self.selected = 0;
+    }
+
+    /// Selects item `at` among the shown ones.
+    pub fn select(&mut self, at: usize) {
+        self.selected = cmp::min(at, self.shown.len().saturating_sub(1));
+    }
+
+    /// Handles a key; returns what was picked once the picker is done.
+    pub fn key(&mut self, code: KeyCode) -> Option<Picked> {
+        match code {
+            KeyCode::Esc => return Some(Picked::Cancelled),
+            KeyCode::Enter => return self.shown.get(self.selected).map(|&at| Picked::Item(at)),
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down if self.selected + 1 < self.shown.len() => self.selected += 1,
+            KeyCode::PageUp | KeyCode::Home => self.selected = 0,
+            KeyCode::PageDown | KeyCode::End => self.selected = self.shown.len().saturating_sub(1),
+            KeyCode::Backspace => {
+                self.filter.pop();
+                self.update_filter();
+            }
+            KeyCode::Char(ch) => {
+                self.filter.push(ch);
+                self.update_filter();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// The header line followed by at most `height - 1` items, scrolled so
+    /// the selected one is among them, as (text, is selected).
+    pub fn lines(&self, height: usize, width: usize) -> Vec<(String, bool)> {
+        let header = format!("{} ({}/{}): {}", self.title, self.shown.len(), self.items.len(), self.filter);
+        let mut lines = vec![(String::from(unicode::truncate_to_width(&header, width)), false)];
+        let rows = height.saturating_sub(1);
+        let first = (self.selected + 1).saturating_sub(rows);
+        for (at, &item) in self.shown.iter().enumerate().skip(first).take(rows) {
+            lines.push((String::from(unicode::truncate_to_width(&self.items[item], width)), at == self.selected));
+        }
+        lines
+    }
+
+    /// Rows the picker wants: the header and one per shown item.
+    pub fn height(&self) -> usize {
+        self.shown.len() + 1
+    }
+}
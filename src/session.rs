@@ -0,0 +1,216 @@
+// Sessions: the open file, where the cursor and the view were, and the
+// bookmarks, saved on quit and put back on the next start with the same
+// `--session NAME` (or `--restore`, which names the session after the
+// current directory). The file is a small subset of TOML with a version;
+// keys it doesn't know are skipped, so newer sessions still load.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const VERSION: u32 = 1;
+
+#[derive(Default)]
+pub struct Session {
+    pub file: Option<String>,
+    pub cursor: (usize, usize),
+    pub row_offset: usize,
+    pub bookmarks: Vec<(String, Vec<usize>)>,
+}
+
+/// Where session `name` is kept: `$XDG_STATE_HOME/rte/sessions`, or
+/// `~/.local/state/rte/sessions` without it.
+pub fn path(name: &str) -> Option<PathBuf> {
+    let state = env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))?;
+    Some(state.join("rte/sessions").join(name))
+}
+
+/// The session name `--restore` uses for `dir`: its path with the
+/// slashes swapped for `%`, as Vim names swap files.
+pub fn name_for_dir(dir: &Path) -> String {
+    dir.to_string_lossy().replace('/', "%")
+}
+
+impl Session {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let invalid = |at: usize, what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", at + 1, what));
+        let mut session = Session::default();
+        let mut version = None;
+        // the [[table]] the keys are in, "" before any
+        let mut table = "";
+        for (at, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix("[[").and_then(|line| line.strip_suffix("]]")) {
+                table = name.trim();
+                if table == "bookmarks" {
+                    session.bookmarks.push((String::new(), Vec::new()));
+                }
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| invalid(at, "expected key = value"))?;
+            let (key, value) = (key.trim(), value.trim());
+            let bad_value = || invalid(at, &format!("bad value for {}", key));
+            match (table, key) {
+                ("", "version") => version = Some(value.parse::<u32>().map_err(|_| bad_value())?),
+                ("", "file") => session.file = Some(unquote(value).ok_or_else(bad_value)?),
+                ("", "cursor") => match numbers(value).as_deref() {
+                    Some(&[cursor_x, cursor_y]) => session.cursor = (cursor_x, cursor_y),
+                    _ => return Err(bad_value()),
+                },
+                ("", "row_offset") => session.row_offset = value.parse().map_err(|_| bad_value())?,
+                ("bookmarks", "file") => {
+                    session.bookmarks.last_mut().unwrap().0 = unquote(value).ok_or_else(bad_value)?
+                }
+                ("bookmarks", "rows") => {
+                    session.bookmarks.last_mut().unwrap().1 = numbers(value).ok_or_else(bad_value)?
+                }
+                _ => log::debug!("skipping {} in {}", key, path.display()),
+            }
+        }
+        match version {
+            Some(_) => Ok(session),
+            None => Err(invalid(0, "not a session: no version")),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut contents = format!("version = {}\n", VERSION);
+        if let Some(file) = &self.file {
+            contents.push_str(&format!("file = {}\n", quote(file)));
+        }
+        contents.push_str(&format!("cursor = [{}, {}]\n", self.cursor.0, self.cursor.1));
+        contents.push_str(&format!("row_offset = {}\n", self.row_offset));
+        for (file, rows) in &self.bookmarks {
+            let rows: Vec<String> = rows.iter().map(usize::to_string).collect();
+            contents.push_str(&format!("\n[[bookmarks]]\nfile = {}\nrows = [{}]\n", quote(file), rows.join(", ")));
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, contents)
+    }
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn unquote(value: &str) -> Option<String> {
+    let inner = value.strip_prefix('"')?.strip_suffix('"')?;
+    let mut text = String::new();
+    let mut chars = inner.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => text.push(chars.next()?),
+            '"' => return None,
+            _ => text.push(ch),
+        }
+    }
+    Some(text)
+}
+
+/// `[1, 2, 3]`
+fn numbers(value: &str) -> Option<Vec<usize>> {
+    let inner = value.strip_prefix('[')?.strip_suffix(']')?;
+    inner.split(',').map(str::trim).filter(|number| !number.is_empty()).map(|number| number.parse().ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A path under the temp directory for session `name`, with `contents`
+    /// written to it if given.
+    fn session_file(name: &str, contents: Option<&str>) -> PathBuf {
+        let path = env::temp_dir().join(format!("rte-session-{}-{}", name, std::process::id())).join("session");
+        let _ = fs::remove_file(&path);
+        if let Some(contents) = contents {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, contents).unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = session_file("round-trip", None);
+        let session = Session {
+            file: Some(String::from("dir/a \"quoted\" \\ name.txt")),
+            cursor: (3, 140),
+            row_offset: 120,
+            bookmarks: vec![(String::from("one.rs"), vec![0, 9, 41]), (String::from("two.rs"), Vec::new())],
+        };
+        session.save(&path).unwrap();
+        let loaded = Session::load(&path).unwrap();
+        assert_eq!(loaded.file, session.file);
+        assert_eq!(loaded.cursor, (3, 140));
+        assert_eq!(loaded.row_offset, 120);
+        assert_eq!(loaded.bookmarks, session.bookmarks);
+
+        let path = session_file("no-file", None);
+        Session::default().save(&path).unwrap();
+        let loaded = Session::load(&path).unwrap();
+        assert!(loaded.file.is_none() && loaded.bookmarks.is_empty());
+    }
+
+    #[test]
+    fn newer_sessions_load() {
+        let contents = "\
+            # written by a later rte\n\
+            version = 7\n\
+            file = \"main.rs\"\n\
+            folds = [1, 2]\n\
+            row_offset = 4\n\
+            \n\
+            [[bookmarks]]\n\
+            file = \"main.rs\"\n\
+            rows = [5]\n\
+            colour = \"red\"\n\
+            \n\
+            [[marks]]\n\
+            file = \"other.rs\"\n\
+            rows = [1]\n";
+        let loaded = Session::load(&session_file("newer", Some(contents))).unwrap();
+        assert_eq!(loaded.file.as_deref(), Some("main.rs"));
+        assert_eq!(loaded.row_offset, 4);
+        assert_eq!(loaded.bookmarks, vec![(String::from("main.rs"), vec![5])]);
+    }
+
+    /// Why the session `contents` doesn't load.
+    fn error(name: &str, contents: Option<&str>) -> String {
+        match Session::load(&session_file(name, contents)) {
+            Ok(_) => panic!("{} loaded", name),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    #[test]
+    fn not_sessions() {
+        assert_eq!(error("unversioned", Some("file = \"main.rs\"\n")), "line 1: not a session: no version");
+        assert_eq!(error("bad-cursor", Some("version = 1\ncursor = [1]\n")), "line 2: bad value for cursor");
+        assert_eq!(error("no-equals", Some("version = 1\njunk\n")), "line 2: expected key = value");
+        assert!(error("missing", None).contains("No such file"));
+    }
+
+    #[test]
+    fn quoting() {
+        for text in ["plain", "", "a \"b\"", "back\\slash\\", "tab\there"] {
+            assert_eq!(unquote(&quote(text)).as_deref(), Some(text));
+        }
+        assert_eq!(unquote("unquoted"), None);
+        assert_eq!(unquote("\"stray \" quote\""), None);
+        assert_eq!(unquote("\"dangling\\\""), None);
+    }
+
+    #[test]
+    fn directory_names() {
+        assert_eq!(name_for_dir(Path::new("/home/me/project")), "%home%me%project");
+    }
+}
@@ -0,0 +1,47 @@
+// SIGTERM and SIGHUP (the terminal closing, or an SSH connection
+// dropping). The handler only records the signal and wakes the main
+// loop, which saves the session, restores the terminal and exits with
+// 128 + the signal's number, as a shell reports a process killed by it.
+
+#[cfg(unix)]
+use std::sync::atomic::{AtomicI32, Ordering};
+
+#[cfg(unix)]
+static RECEIVED: AtomicI32 = AtomicI32::new(0);
+
+#[cfg(unix)]
+extern "C" fn handle(signal: libc::c_int) {
+    RECEIVED.store(signal, Ordering::SeqCst);
+    // only async-signal-safe calls here: crossterm turns the SIGWINCH
+    // into a resize event, which gets the main loop out of its poll
+    unsafe {
+        libc::kill(libc::getpid(), libc::SIGWINCH);
+    }
+}
+
+#[cfg(unix)]
+pub fn install() {
+    for signal in [libc::SIGTERM, libc::SIGHUP] {
+        let handler = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        if unsafe { libc::signal(signal, handler) } == libc::SIG_ERR {
+            log::warn!("can't handle signal {}: {}", signal, std::io::Error::last_os_error());
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn install() {}
+
+/// The signal that asked the editor to quit, if one has.
+#[cfg(unix)]
+pub fn received() -> Option<i32> {
+    match RECEIVED.load(Ordering::SeqCst) {
+        0 => None,
+        signal => Some(signal),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn received() -> Option<i32> {
+    None
+}
@@ -0,0 +1,234 @@
+// Spell checking against a word list: the system one (RTE_DICTIONARY,
+// /usr/share/dict/words by default) and a personal one the user adds words
+// to (RTE_PERSONAL_DICTIONARY, ~/.rte_words by default), one word a line.
+
+use std::collections::HashSet;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+const SYSTEM_DICTIONARY: &str = "/usr/share/dict/words";
+const SUGGESTIONS: usize = 5;
+/// Longest word that suggestions two edits away are looked for: there are
+/// about as many of those as the square of the single edits, hundreds of
+/// thousands already at this length.
+const SECOND_EDIT_LENGTH: usize = 8;
+
+pub struct Dictionary {
+    words: HashSet<String>,
+    personal: Option<PathBuf>,
+}
+
+impl Dictionary {
+    /// Fails if the system word list can't be read; a personal one that
+    /// doesn't exist yet is fine.
+    pub fn load() -> io::Result<Self> {
+        let system = env::var_os("RTE_DICTIONARY").map_or_else(|| PathBuf::from(SYSTEM_DICTIONARY), PathBuf::from);
+        let personal = env::var_os("RTE_PERSONAL_DICTIONARY")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".rte_words")));
+        let mut words = HashSet::new();
+        let mut add = |contents: String| words.extend(contents.lines().map(|word| word.trim().to_string()));
+        add(fs::read_to_string(&system)?);
+        if let Some(contents) = personal.as_ref().and_then(|personal| fs::read_to_string(personal).ok()) {
+            add(contents);
+        }
+        log::debug!("{} words in the dictionary", words.len());
+        Ok(Self { words, personal })
+    }
+
+    /// A word is known as written, or in lower case, so that one starting
+    /// a sentence or written in capitals still matches; one in capitals
+    /// also matches the name it spells (PARIS, Paris).
+    pub fn knows(&self, word: &str) -> bool {
+        if self.words.contains(word) || self.words.contains(&word.to_lowercase()) {
+            return true;
+        }
+        if word.chars().any(char::is_lowercase) {
+            return false;
+        }
+        let mut chars = word.chars();
+        let capitalized = chars.next().map(|first| first.to_uppercase().chain(chars.flat_map(char::to_lowercase)));
+        capitalized.is_some_and(|capitalized| self.words.contains(&capitalized.collect::<String>()))
+    }
+
+    /// Byte ranges of the words in `line` the dictionary doesn't know.
+    /// Single letters and words with digits in them are left alone.
+    pub fn misspelled(&self, line: &str) -> Vec<(usize, usize)> {
+        words(line)
+            .filter(|&(start, end)| {
+                let word = &line[start..end];
+                word.chars().nth(1).is_some() && !self.knows(word)
+            })
+            .collect()
+    }
+
+    /// Known words one edit away from `word`, or two if none is one away
+    /// and the word is short enough to look.
+    pub fn suggestions(&self, word: &str) -> Vec<String> {
+        let lower = word.to_lowercase();
+        let known = |candidates: &mut dyn Iterator<Item = String>, limit: usize| -> Vec<String> {
+            let mut found: Vec<String> = Vec::new();
+            for candidate in candidates {
+                if self.words.contains(&candidate) && !found.contains(&candidate) {
+                    found.push(candidate);
+                    if found.len() == limit {
+                        break;
+                    }
+                }
+            }
+            found
+        };
+        let mut found = known(&mut edits(&lower).into_iter(), usize::MAX);
+        if found.is_empty() && lower.chars().count() <= SECOND_EDIT_LENGTH {
+            let mut seen = HashSet::new();
+            let mut first = edits(&lower);
+            first.retain(|edit| seen.insert(edit.clone()));
+            found = known(&mut first.iter().flat_map(|edit| edits(edit)), SUGGESTIONS);
+        }
+        found.truncate(SUGGESTIONS);
+        // keep the capital if the word started with one
+        if word.chars().next().is_some_and(char::is_uppercase) {
+            for suggestion in &mut found {
+                let mut chars = suggestion.chars();
+                *suggestion = chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default();
+            }
+        }
+        found
+    }
+
+    /// Appends `word` to the personal dictionary.
+    pub fn add(&mut self, word: &str) -> io::Result<()> {
+        let personal = match &self.personal {
+            Some(personal) => personal,
+            None => return Err(io::Error::new(io::ErrorKind::NotFound, "no personal dictionary (set HOME)")),
+        };
+        let mut file = OpenOptions::new().create(true).append(true).open(personal)?;
+        writeln!(file, "{}", word)?;
+        self.words.insert(word.to_string());
+        Ok(())
+    }
+}
+
+/// Byte ranges of the words in `line`: runs of letters, with apostrophes
+/// between letters (don't, l'état) counted as part of the word.
+pub fn words(line: &str) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let mut chars = line.char_indices().peekable();
+    std::iter::from_fn(move || loop {
+        let (start, first) = chars.next()?;
+        if !first.is_alphanumeric() {
+            continue;
+        }
+        let mut end = start + first.len_utf8();
+        let mut digits = first.is_numeric();
+        while let Some(&(at, ch)) = chars.peek() {
+            let apostrophe = (ch == '\'' || ch == '\u{2019}') && line[at + ch.len_utf8()..].starts_with(char::is_alphabetic);
+            if !ch.is_alphanumeric() && !apostrophe {
+                break;
+            }
+            digits |= ch.is_numeric();
+            end = at + ch.len_utf8();
+            chars.next();
+        }
+        if !digits {
+            return Some((start, end));
+        }
+    })
+}
+
+/// Every string one deletion, transposition, replacement or insertion of a
+/// letter away from `word`.
+fn edits(word: &str) -> Vec<String> {
+    let chars: Vec<char> = word.chars().collect();
+    let mut letters: Vec<char> = ('a'..='z').collect();
+    for &ch in &chars {
+        if !letters.contains(&ch) {
+            letters.push(ch);
+        }
+    }
+    let mut edits = Vec::new();
+    for at in 0..=chars.len() {
+        let (before, after) = chars.split_at(at);
+        let text = |middle: &[char], rest: &[char]| before.iter().chain(middle).chain(rest).collect::<String>();
+        if let Some((_, rest)) = after.split_first() {
+            edits.push(text(&[], rest));
+            for &letter in &letters {
+                edits.push(text(&[letter], rest));
+            }
+        }
+        if let [first, second, rest @ ..] = after {
+            edits.push(text(&[*second, *first], rest));
+        }
+        for &letter in &letters {
+            edits.push(text(&[letter], after));
+        }
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary(words: &[&str]) -> Dictionary {
+        Dictionary {
+            words: words.iter().map(|word| word.to_string()).collect(),
+            personal: None,
+        }
+    }
+
+    fn texts(line: &str) -> Vec<&str> {
+        words(line).map(|(start, end)| &line[start..end]).collect()
+    }
+
+    #[test]
+    fn words_in_a_line() {
+        let line = "don't stop, l'\u{e9}tat\u{2019}s -- fine";
+        assert_eq!(texts(line), ["don't", "stop", "l'\u{e9}tat\u{2019}s", "fine"]);
+        // apostrophes only count between letters
+        assert_eq!(texts("'quoted' rock'n'roll dogs'"), ["quoted", "rock'n'roll", "dogs"]);
+        // words with digits are left out whole
+        assert_eq!(texts("utf8 and x86_64 or 42"), ["and", "or"]);
+        assert_eq!(texts("\u{4e2d}\u{6587} text"), ["\u{4e2d}\u{6587}", "text"]);
+    }
+
+    #[test]
+    fn capitals() {
+        let dictionary = dictionary(&["hello", "Paris", "NASA"]);
+        assert!(dictionary.knows("hello") && dictionary.knows("Hello") && dictionary.knows("HELLO"));
+        assert!(dictionary.knows("Paris") && dictionary.knows("PARIS"));
+        assert!(!dictionary.knows("paris"));
+        assert!(dictionary.knows("NASA") && !dictionary.knows("Nasa"));
+    }
+
+    #[test]
+    fn misspelled_words() {
+        let dictionary = dictionary(&["the", "cat", "sat"]);
+        let line = "The cta sat on a mat2";
+        let ranges: Vec<&str> = dictionary.misspelled(line).into_iter().map(|(start, end)| &line[start..end]).collect();
+        assert_eq!(ranges, ["cta", "on"]);
+    }
+
+    #[test]
+    fn suggestions() {
+        let dictionary = dictionary(&["cat", "cart", "act", "at", "coat", "chat", "dog", "receive"]);
+        let mut one_away = dictionary.suggestions("cot");
+        one_away.sort();
+        assert_eq!(one_away, ["cat", "coat"]);
+        assert_eq!(dictionary.suggestions("Dgo"), ["Dog"]);
+        // two edits, when nothing is one away
+        assert_eq!(dictionary.suggestions("recieev"), ["receive"]);
+        assert_eq!(dictionary.suggestions("cccccccc").len(), 0);
+        assert!(dictionary.suggestions("zzzzz").is_empty());
+        assert!(dictionary.suggestions("cat").len() <= SUGGESTIONS);
+    }
+
+    #[test]
+    fn long_words_get_single_edits_only() {
+        let dictionary = dictionary(&["internationalisation", "shorter"]);
+        assert_eq!(dictionary.suggestions("internationalisatoin"), ["internationalisation"]);
+        assert!(dictionary.suggestions("internationalisatoinx").is_empty());
+        assert_eq!(dictionary.suggestions("shortre!"), ["shorter"]);
+    }
+}
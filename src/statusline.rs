@@ -0,0 +1,195 @@
+// The status bar, drawn from a template given in RTE_STATUS (RTE_TITLE
+// takes the same kind of template for the window title), e.g.
+//
+//     {filename}{modified}{missing} | {filetype} |=| {line}/{total_lines}:{col} {percent}%
+//
+// `|=|` splits it into a left-aligned and a right-aligned part, and
+// ` | ` splits each part into segments. When the bar doesn't fit, whole
+// segments are dropped, rightmost first: from the right part, then from
+// the left one, which always keeps its first segment.
+
+use crate::unicode;
+
+const PLACEHOLDERS: [&str; 11] = [
+    "filename",
+    "modified",
+    "filetype",
+    "line",
+    "col",
+    "total_lines",
+    "percent",
+    "encoding",
+    "line_ending",
+    "readonly",
+    "missing",
+];
+
+/// Values for the placeholders, in the order of PLACEHOLDERS.
+pub type Values = [String; 11];
+
+enum Piece {
+    Text(String),
+    /// Index into PLACEHOLDERS.
+    Field(usize),
+}
+
+type Segment = Vec<Piece>;
+
+pub struct StatusLine {
+    left: Vec<Segment>,
+    right: Vec<Segment>,
+}
+
+impl StatusLine {
+    /// Also returns the placeholders it didn't know, which are kept as
+    /// they were written.
+    pub fn parse(template: &str) -> (Self, Vec<String>) {
+        let mut unknown = Vec::new();
+        let (left, right) = template.split_once("|=|").unwrap_or((template, ""));
+        let mut segments = |part: &str| -> Vec<Segment> {
+            part.split(" | ")
+                .map(|segment| pieces(segment, &mut unknown))
+                .filter(|segment| !segment.is_empty())
+                .collect()
+        };
+        let status_line = Self {
+            left: segments(left.trim_end()),
+            right: segments(right.trim_start()),
+        };
+        (status_line, unknown)
+    }
+
+    pub fn render(&self, values: &Values, width: usize) -> String {
+        let (mut left, mut right) = (render_part(&self.left, values), render_part(&self.right, values));
+        let joined_width = |segments: &[String]| unicode::str_width(&segments.join(" | "));
+        // at least a space between the parts, when there are two
+        let gap = |left: &[String], right: &[String]| (!left.is_empty() && !right.is_empty()) as usize;
+        while joined_width(&left) + joined_width(&right) + gap(&left, &right) > width {
+            if right.pop().is_none() && (left.len() < 2 || left.pop().is_none()) {
+                break;
+            }
+        }
+        let (left, right) = (left.join(" | "), right.join(" | "));
+        let gap = width.saturating_sub(unicode::str_width(&left) + unicode::str_width(&right));
+        let line = format!("{}{}{}", left, " ".repeat(gap), right);
+        String::from(unicode::truncate_to_width(&line, width))
+    }
+
+    /// The whole template filled in on one line, for the window title:
+    /// the parts are separated by a space and nothing is dropped.
+    pub fn text(&self, values: &Values) -> String {
+        let (left, right) = (render_part(&self.left, values), render_part(&self.right, values));
+        match (left.is_empty(), right.is_empty()) {
+            (_, true) => left.join(" | "),
+            (true, false) => right.join(" | "),
+            (false, false) => format!("{} {}", left.join(" | "), right.join(" | ")),
+        }
+    }
+}
+
+/// The filled-in segments of a part, leaving out those that came out
+/// empty.
+fn render_part(segments: &[Segment], values: &Values) -> Vec<String> {
+    segments
+        .iter()
+        .map(|segment| {
+            segment
+                .iter()
+                .map(|piece| match piece {
+                    Piece::Text(text) => text.as_str(),
+                    Piece::Field(at) => values[*at].as_str(),
+                })
+                .collect::<String>()
+        })
+        .filter(|segment| !segment.is_empty())
+        .collect()
+}
+
+/// Splits `{placeholder}`s out of the text around them.
+fn pieces(segment: &str, unknown: &mut Vec<String>) -> Segment {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut rest = segment;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find('}') {
+            Some(end) => end,
+            None => break,
+        };
+        let name = &rest[1..end];
+        match PLACEHOLDERS.iter().position(|&placeholder| placeholder == name) {
+            Some(at) => {
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(Piece::Field(at));
+            }
+            None => {
+                unknown.push(rest[..=end].to_string());
+                text.push_str(&rest[..=end]);
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    text.push_str(rest);
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values() -> Values {
+        let mut values = Values::default();
+        values[0] = String::from("main.rs");
+        values[2] = String::from("rust");
+        values[3] = String::from("12");
+        values[4] = String::from("3");
+        values[5] = String::from("200");
+        values
+    }
+
+    fn render(template: &str, width: usize) -> String {
+        StatusLine::parse(template).0.render(&values(), width)
+    }
+
+    #[test]
+    fn unknown_placeholders_stay_as_written() {
+        let (status_line, unknown) = StatusLine::parse("{filename} {branch}{line");
+        assert_eq!(unknown, ["{branch}"]);
+        assert_eq!(status_line.text(&values()), "main.rs {branch}{line");
+    }
+
+    #[test]
+    fn parts_and_segments() {
+        let template = "{filename} | {filetype} |=| {line}/{total_lines}:{col}";
+        assert_eq!(render(template, 30), "main.rs | rust        12/200:3");
+        assert_eq!(StatusLine::parse(template).0.text(&values()), "main.rs | rust 12/200:3");
+        // empty segments are left out, separators and all
+        assert_eq!(render("{filename} | {modified} | {filetype}", 20), "main.rs | rust      ");
+        assert_eq!(render("|=| {line}", 6), "    12");
+        assert_eq!(StatusLine::parse("|=| {line}").0.text(&values()), "12");
+    }
+
+    #[test]
+    fn segments_drop_from_the_right() {
+        let template = "{filename} | {filetype} |=| {line} | {total_lines}";
+        assert_eq!(render(template, 23), "main.rs | rust 12 | 200");
+        assert_eq!(render(template, 22), "main.rs | rust      12");
+        assert_eq!(render(template, 17), "main.rs | rust 12");
+        assert_eq!(render(template, 16), "main.rs | rust  ");
+        assert_eq!(render(template, 10), "main.rs   ");
+        // the first segment stays, cut short if need be
+        assert_eq!(render(template, 4), "main");
+    }
+
+    #[test]
+    fn a_left_part_alone_fills_the_width() {
+        assert_eq!(render("{filename} | {filetype}", 14), "main.rs | rust");
+        assert_eq!(render("{filename} | {filetype}", 13), "main.rs      ");
+    }
+}
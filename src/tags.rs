@@ -0,0 +1,305 @@
+// Lookups in a ctags `tags` file (Exuberant / Universal ctags format).
+// Sorted files are binary searched with seeks, so a tags file for a huge
+// tree never has to be read into memory.
+
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+pub enum Address {
+    Line(usize),
+    /// A `/pattern/` address: the line itself, as a literal.
+    Pattern { text: String, start: bool, end: bool },
+}
+
+impl Address {
+    pub fn matches(&self, line: &str) -> bool {
+        match self {
+            Address::Line(_) => false,
+            Address::Pattern { text, start: true, end: true } => line == text,
+            Address::Pattern { text, start: true, end: false } => line.starts_with(text.as_str()),
+            Address::Pattern { text, start: false, end: true } => line.ends_with(text.as_str()),
+            Address::Pattern { text, .. } => line.contains(text.as_str()),
+        }
+    }
+}
+
+pub struct Tag {
+    pub file: PathBuf,
+    pub address: Address,
+    /// The `kind` extension field, if any: `f`, `function`, `s`, ...
+    pub kind: Option<String>,
+}
+
+/// The nearest `tags` file in `dir` or one of its parents.
+pub fn find(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().map(|dir| dir.join("tags")).find(|path| path.is_file())
+}
+
+/// Every tag named `name`. Files are relative to the tags file's directory.
+pub fn lookup(path: &Path, name: &str) -> io::Result<Vec<Tag>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let compare: Option<fn(&str, &str) -> Ordering> = match sorted(&mut reader)? {
+        1 => Some(|a, b| a.cmp(b)),
+        2 => Some(|a, b| a.to_lowercase().cmp(&b.to_lowercase())),
+        _ => None,
+    };
+    let (mut low, mut high) = (0, reader.get_ref().metadata()?.len());
+    while let (Some(compare), true) = (compare, low < high) {
+        let middle = low + (high - low) / 2;
+        match line_from(&mut reader, middle)? {
+            Some((start, line)) if compare(tag_name(&line), name) == Ordering::Less => {
+                low = start + line.len() as u64 + 1;
+            }
+            _ => high = middle,
+        }
+    }
+
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut tags = Vec::new();
+    let mut line = match line_from(&mut reader, low)? {
+        Some((_, line)) => line,
+        None => return Ok(tags),
+    };
+    loop {
+        let line_name = tag_name(&line);
+        // past the name in a sorted file; an unsorted one is read to the end
+        if compare.is_some_and(|compare| compare(line_name, name) == Ordering::Greater) {
+            break;
+        }
+        // the header's pseudo-tags aren't definitions
+        if line_name == name && !line.starts_with("!_TAG_") {
+            tags.extend(parse(&line, base));
+        }
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        trim_newline(&mut line);
+    }
+    Ok(tags)
+}
+
+/// The `!_TAG_FILE_SORTED` value from the header: 0 unsorted, 1 sorted,
+/// 2 sorted ignoring case. Files without a header are taken as unsorted.
+fn sorted(reader: &mut BufReader<File>) -> io::Result<u32> {
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && line.starts_with("!_TAG_") {
+        if let Some(value) = line.strip_prefix("!_TAG_FILE_SORTED\t") {
+            return Ok(value.split('\t').next().and_then(|value| value.parse().ok()).unwrap_or(0));
+        }
+        line.clear();
+    }
+    Ok(0)
+}
+
+/// The first whole line starting at or after `offset`, and where it starts.
+fn line_from(reader: &mut BufReader<File>, offset: u64) -> io::Result<Option<(u64, String)>> {
+    let mut line = String::new();
+    let start = if offset == 0 {
+        reader.seek(SeekFrom::Start(0))?
+    } else {
+        reader.seek(SeekFrom::Start(offset - 1))?;
+        let mut partial = Vec::new();
+        offset - 1 + reader.read_until(b'\n', &mut partial)? as u64
+    };
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    trim_newline(&mut line);
+    Ok(Some((start, line)))
+}
+
+/// Leaves the `\n` off; a `\r` before it is kept in the line's length as
+/// far as seeking is concerned, so it's only dropped when parsing.
+fn trim_newline(line: &mut String) {
+    if line.ends_with('\n') {
+        line.pop();
+    }
+}
+
+fn tag_name(line: &str) -> &str {
+    line.split('\t').next().unwrap_or_default()
+}
+
+/// `name<TAB>file<TAB>address;"<TAB>kind...`, where the address is a line
+/// number or a `/pattern/` (`?pattern?` searches backwards, which doesn't
+/// matter for a literal match).
+fn parse(line: &str, base: &Path) -> Option<Tag> {
+    let line = line.trim_end_matches('\r');
+    let mut fields = line.splitn(3, '\t');
+    let (_, file, rest) = (fields.next()?, fields.next()?, fields.next()?);
+    let (address, rest) = match rest.chars().next()? {
+        delimiter @ ('/' | '?') => {
+            let (text, length) = pattern(&rest[1..], delimiter)?;
+            let start = text.starts_with('^');
+            let end = text.ends_with('$') && !text.ends_with("\\$");
+            let text = text[start as usize..text.len() - end as usize].to_string();
+            (Address::Pattern { text, start, end }, &rest[1 + length..])
+        }
+        _ => {
+            let digits = rest.find(|ch: char| !ch.is_ascii_digit()).unwrap_or(rest.len());
+            (Address::Line(rest[..digits].parse().ok()?), &rest[digits..])
+        }
+    };
+    let kind = rest.strip_prefix(";\"").and_then(|fields| {
+        fields.split('\t').filter(|field| !field.is_empty()).find_map(|field| match field.split_once(':') {
+            Some(("kind", kind)) => Some(kind.to_string()),
+            Some(_) => None,
+            None => Some(field.to_string()),
+        })
+    });
+    Some(Tag {
+        file: base.join(file),
+        address,
+        kind,
+    })
+}
+
+/// The pattern up to the closing `delimiter`, unescaped, and how many bytes
+/// it took including the delimiter.
+fn pattern(text: &str, delimiter: char) -> Option<(String, usize)> {
+    let mut pattern = String::new();
+    let mut chars = text.char_indices();
+    while let Some((at, ch)) = chars.next() {
+        match ch {
+            '\\' => match chars.next() {
+                Some((_, escaped @ ('\\' | '/' | '?'))) => pattern.push(escaped),
+                Some((_, other)) => {
+                    pattern.push('\\');
+                    pattern.push(other);
+                }
+                None => return None,
+            },
+            _ if ch == delimiter => return Some((pattern, at + 1)),
+            _ => pattern.push(ch),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// A tags file under the temp directory with `header` and then `lines`.
+    fn tags_file(name: &str, header: &str, lines: &[String]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rte-tags-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tags");
+        fs::write(&path, format!("{}{}\n", header, lines.join("\n"))).unwrap();
+        path
+    }
+
+    const SORTED: &str = "!_TAG_FILE_FORMAT\t2\t/extended format/\n!_TAG_FILE_SORTED\t1\t/0=unsorted, 1=sorted/\n";
+
+    /// Enough tags that the binary search has work to do, with three
+    /// `dup` tags in the middle.
+    fn many() -> Vec<String> {
+        let mut lines: Vec<String> = (0..300).map(|at| format!("tag{:03}\tsrc/t.rs\t{};\"\tf", at, at + 1)).collect();
+        lines.extend((0..3).map(|at| format!("dup\tsrc/d{}.rs\t/^fn dup() {{$/;\"\tf", at)));
+        lines.sort();
+        lines
+    }
+
+    fn lines(tags: &[Tag]) -> Vec<usize> {
+        tags.iter()
+            .map(|tag| match tag.address {
+                Address::Line(line) => line,
+                Address::Pattern { .. } => 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn first_last_and_missing_in_a_sorted_file() {
+        let path = tags_file("sorted", SORTED, &many());
+        assert_eq!(lines(&lookup(&path, "tag000").unwrap()), [1]);
+        assert_eq!(lines(&lookup(&path, "tag299").unwrap()), [300]);
+        assert_eq!(lines(&lookup(&path, "tag150").unwrap()), [151]);
+        assert!(lookup(&path, "tag").unwrap().is_empty());
+        assert!(lookup(&path, "tag3000").unwrap().is_empty());
+        assert!(lookup(&path, "aaa").unwrap().is_empty());
+        assert!(lookup(&path, "zzz").unwrap().is_empty());
+        // the header isn't a tag
+        assert!(lookup(&path, "!_TAG_FILE_SORTED").unwrap().is_empty());
+    }
+
+    #[test]
+    fn every_tag_with_the_name() {
+        let path = tags_file("duplicates", SORTED, &many());
+        let files: Vec<PathBuf> = lookup(&path, "dup").unwrap().into_iter().map(|tag| tag.file).collect();
+        let dir = path.parent().unwrap();
+        assert_eq!(files, [dir.join("src/d0.rs"), dir.join("src/d1.rs"), dir.join("src/d2.rs")]);
+    }
+
+    #[test]
+    fn unsorted_and_case_folded_files() {
+        let mut reversed = many();
+        reversed.reverse();
+        let path = tags_file("unsorted", "", &reversed);
+        assert_eq!(lines(&lookup(&path, "tag007").unwrap()), [8]);
+        assert_eq!(lookup(&path, "dup").unwrap().len(), 3);
+
+        let mut folded: Vec<String> = ["Gamma\tc.rs\t3", "Alpha\ta.rs\t1", "beta\tb.rs\t2"].map(String::from).into();
+        folded.sort_by_key(|line| line.to_lowercase());
+        let path = tags_file("folded", "!_TAG_FILE_SORTED\t2\t/2=foldcase/\n", &folded);
+        assert_eq!(lines(&lookup(&path, "Gamma").unwrap()), [3]);
+        assert_eq!(lines(&lookup(&path, "beta").unwrap()), [2]);
+        assert!(lookup(&path, "gamma").unwrap().is_empty());
+    }
+
+    #[test]
+    fn line_number_address() {
+        let tag = parse("main\tsrc/main.rs\t42;\"\tkind:function\tline:42", Path::new("/p")).unwrap();
+        assert_eq!(tag.file, Path::new("/p/src/main.rs"));
+        assert!(matches!(tag.address, Address::Line(42)));
+        assert_eq!(tag.kind.as_deref(), Some("function"));
+        // old style: no ;" and no kind
+        let tag = parse("main\tmain.c\t7", Path::new("")).unwrap();
+        assert!(matches!(tag.address, Address::Line(7)) && tag.kind.is_none());
+        assert!(parse("main\tmain.c\tnowhere", Path::new("")).is_none());
+    }
+
+    #[test]
+    fn pattern_addresses() {
+        let tag = parse("main\tmain.rs\t/^fn main() {$/;\"\tf\r", Path::new("")).unwrap();
+        assert_eq!(tag.kind.as_deref(), Some("f"));
+        match tag.address {
+            Address::Pattern { text, start, end } => {
+                assert_eq!((text.as_str(), start, end), ("fn main() {", true, true))
+            }
+            Address::Line(_) => panic!("not a pattern"),
+        }
+        // escaped delimiters and backslashes; an escaped $ is no anchor
+        let tag = parse("path\tp.rs\t/^let path = \"a\\/b\\\\c\\$/;\"\tv", Path::new("")).unwrap();
+        match tag.address {
+            Address::Pattern { text, start, end } => {
+                assert_eq!((text.as_str(), start, end), ("let path = \"a/b\\c\\$", true, false))
+            }
+            Address::Line(_) => panic!("not a pattern"),
+        }
+        let tag = parse("up\tu.rs\t?^up?", Path::new("")).unwrap();
+        assert!(matches!(tag.address, Address::Pattern { ref text, start: true, end: false } if text == "up"));
+        // never closed
+        assert!(parse("bad\tb.rs\t/^fn bad(", Path::new("")).is_none());
+    }
+
+    #[test]
+    fn pattern_matching() {
+        let pattern = |text: &str, start, end| Address::Pattern {
+            text: text.to_string(),
+            start,
+            end,
+        };
+        assert!(pattern("fn main", true, true).matches("fn main"));
+        assert!(!pattern("fn main", true, true).matches("fn main()"));
+        assert!(pattern("fn main", true, false).matches("fn main()"));
+        assert!(!pattern("fn main", true, false).matches("pub fn main()"));
+        assert!(pattern("main()", false, true).matches("pub fn main()"));
+        assert!(pattern("main", false, false).matches("pub fn main()"));
+        assert!(!Address::Line(1).matches("anything"));
+    }
+}
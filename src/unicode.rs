@@ -0,0 +1,579 @@
+// A small grapheme cluster segmenter.
+//
+// This follows the extended grapheme cluster rules of UAX #29 closely
+// enough for text editing: combining marks, spacing marks, variation
+// selectors and emoji modifiers stay with their base character, ZWJ
+// emoji sequences and regional indicator pairs (flags) form one cluster,
+// Hangul jamo sequences join, and CR LF is kept together.
+
+#[derive(Clone, Copy, PartialEq)]
+enum Class {
+    Cr,
+    Lf,
+    Control,
+    Extend,
+    Zwj,
+    RegionalIndicator,
+    Prepend,
+    SpacingMark,
+    L,
+    V,
+    T,
+    Lv,
+    Lvt,
+    Pictographic,
+    Other,
+}
+
+const EXTEND: &[(u32, u32)] = &[
+    (0x0300, 0x036F),
+    (0x0483, 0x0489),
+    (0x0591, 0x05BD),
+    (0x05BF, 0x05BF),
+    (0x05C1, 0x05C2),
+    (0x05C4, 0x05C5),
+    (0x05C7, 0x05C7),
+    (0x0610, 0x061A),
+    (0x064B, 0x065F),
+    (0x0670, 0x0670),
+    (0x06D6, 0x06DC),
+    (0x06DF, 0x06E4),
+    (0x06E7, 0x06E8),
+    (0x06EA, 0x06ED),
+    (0x0711, 0x0711),
+    (0x0730, 0x074A),
+    (0x07A6, 0x07B0),
+    (0x07EB, 0x07F3),
+    (0x0816, 0x082D),
+    (0x0859, 0x085B),
+    (0x08D3, 0x08E1),
+    (0x08E3, 0x0902),
+    (0x093A, 0x093A),
+    (0x093C, 0x093C),
+    (0x0941, 0x0948),
+    (0x094D, 0x094D),
+    (0x0951, 0x0957),
+    (0x0962, 0x0963),
+    (0x0981, 0x0981),
+    (0x09BC, 0x09BC),
+    (0x09C1, 0x09C4),
+    (0x09CD, 0x09CD),
+    (0x09E2, 0x09E3),
+    (0x0A01, 0x0A02),
+    (0x0A3C, 0x0A3C),
+    (0x0A41, 0x0A51),
+    (0x0A70, 0x0A71),
+    (0x0A81, 0x0A82),
+    (0x0ABC, 0x0ABC),
+    (0x0AC1, 0x0AC8),
+    (0x0ACD, 0x0ACD),
+    (0x0B01, 0x0B01),
+    (0x0B3C, 0x0B3C),
+    (0x0B3F, 0x0B3F),
+    (0x0B41, 0x0B44),
+    (0x0B4D, 0x0B4D),
+    (0x0BC0, 0x0BC0),
+    (0x0BCD, 0x0BCD),
+    (0x0C3E, 0x0C40),
+    (0x0C46, 0x0C56),
+    (0x0CBC, 0x0CBC),
+    (0x0CCC, 0x0CCD),
+    (0x0D41, 0x0D44),
+    (0x0D4D, 0x0D4D),
+    (0x0DCA, 0x0DCA),
+    (0x0DD2, 0x0DD6),
+    (0x0E31, 0x0E31),
+    (0x0E34, 0x0E3A),
+    (0x0E47, 0x0E4E),
+    (0x0EB1, 0x0EB1),
+    (0x0EB4, 0x0EBC),
+    (0x0EC8, 0x0ECD),
+    (0x0F18, 0x0F19),
+    (0x0F35, 0x0F39),
+    (0x0F71, 0x0F7E),
+    (0x0F80, 0x0F84),
+    (0x0F86, 0x0F87),
+    (0x0F8D, 0x0FBC),
+    (0x102D, 0x1030),
+    (0x1032, 0x1037),
+    (0x1039, 0x103A),
+    (0x1712, 0x1714),
+    (0x17B4, 0x17B5),
+    (0x17B7, 0x17BD),
+    (0x17C6, 0x17C6),
+    (0x17C9, 0x17D3),
+    (0x180B, 0x180D),
+    (0x1AB0, 0x1AFF),
+    (0x1DC0, 0x1DFF),
+    (0x200C, 0x200C),
+    (0x20D0, 0x20F0),
+    (0x302A, 0x302F),
+    (0x3099, 0x309A),
+    (0xFE00, 0xFE0F),
+    (0xFE20, 0xFE2F),
+    (0xFF9E, 0xFF9F),
+    (0x1F3FB, 0x1F3FF),
+    (0xE0020, 0xE007F),
+    (0xE0100, 0xE01EF),
+];
+
+const SPACING_MARK: &[(u32, u32)] = &[
+    (0x0903, 0x0903),
+    (0x093B, 0x093B),
+    (0x093E, 0x0940),
+    (0x0949, 0x094C),
+    (0x094E, 0x094F),
+    (0x0982, 0x0983),
+    (0x09BF, 0x09C0),
+    (0x09C7, 0x09CC),
+    (0x0A03, 0x0A03),
+    (0x0A3E, 0x0A40),
+    (0x0A83, 0x0A83),
+    (0x0ABE, 0x0AC0),
+    (0x0AC9, 0x0ACC),
+    (0x0B02, 0x0B03),
+    (0x0B40, 0x0B40),
+    (0x0B47, 0x0B4C),
+    (0x0BBF, 0x0BBF),
+    (0x0BC1, 0x0BCC),
+    (0x0C01, 0x0C03),
+    (0x0C41, 0x0C44),
+    (0x0C82, 0x0C83),
+    (0x0CBE, 0x0CCB),
+    (0x0D02, 0x0D03),
+    (0x0D3F, 0x0D40),
+    (0x0D46, 0x0D4C),
+    (0x0D82, 0x0D83),
+    (0x0DD0, 0x0DD1),
+    (0x0DD8, 0x0DDF),
+    (0x0E33, 0x0E33),
+    (0x0EB3, 0x0EB3),
+    (0x0F3E, 0x0F3F),
+    (0x0F7F, 0x0F7F),
+    (0x1031, 0x1031),
+    (0x103B, 0x103C),
+    (0x17B6, 0x17B6),
+    (0x17BE, 0x17C5),
+    (0x17C7, 0x17C8),
+];
+
+const PREPEND: &[(u32, u32)] = &[
+    (0x0600, 0x0605),
+    (0x06DD, 0x06DD),
+    (0x070F, 0x070F),
+    (0x08E2, 0x08E2),
+    (0x0D4E, 0x0D4E),
+    (0x110BD, 0x110BD),
+];
+
+const PICTOGRAPHIC: &[(u32, u32)] = &[
+    (0x00A9, 0x00A9),
+    (0x00AE, 0x00AE),
+    (0x203C, 0x203C),
+    (0x2049, 0x2049),
+    (0x2122, 0x2122),
+    (0x2139, 0x2139),
+    (0x2194, 0x21AA),
+    (0x231A, 0x23FF),
+    (0x24C2, 0x24C2),
+    (0x25AA, 0x25FE),
+    (0x2600, 0x27BF),
+    (0x2934, 0x2935),
+    (0x2B05, 0x2B55),
+    (0x3030, 0x3030),
+    (0x303D, 0x303D),
+    (0x3297, 0x3299),
+    (0x1F000, 0x1F0FF),
+    (0x1F10D, 0x1F1AD),
+    (0x1F201, 0x1F3FA),
+    (0x1F400, 0x1FAFF),
+];
+
+fn in_table(table: &[(u32, u32)], c: u32) -> bool {
+    table
+        .binary_search_by(|&(lo, hi)| {
+            if hi < c {
+                std::cmp::Ordering::Less
+            } else if lo > c {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+fn class(ch: char) -> Class {
+    let c = ch as u32;
+    match c {
+        0x0D => Class::Cr,
+        0x0A => Class::Lf,
+        0x200D => Class::Zwj,
+        0x1F1E6..=0x1F1FF => Class::RegionalIndicator,
+        0x1100..=0x115F | 0xA960..=0xA97C => Class::L,
+        0x1160..=0x11A7 | 0xD7B0..=0xD7C6 => Class::V,
+        0x11A8..=0x11FF | 0xD7CB..=0xD7FB => Class::T,
+        0xAC00..=0xD7A3 => {
+            if (c - 0xAC00).is_multiple_of(28) {
+                Class::Lv
+            } else {
+                Class::Lvt
+            }
+        }
+        0x00..=0x1F | 0x7F..=0x9F | 0x2028 | 0x2029 => Class::Control,
+        _ if in_table(EXTEND, c) => Class::Extend,
+        _ if in_table(SPACING_MARK, c) => Class::SpacingMark,
+        _ if in_table(PREPEND, c) => Class::Prepend,
+        _ if in_table(PICTOGRAPHIC, c) => Class::Pictographic,
+        _ => Class::Other,
+    }
+}
+
+/// Iterator over the grapheme clusters of a string slice.
+pub struct Graphemes<'a> {
+    string: &'a str,
+}
+
+impl<'a> Iterator for Graphemes<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let mut chars = self.string.char_indices();
+        let (_, first) = chars.next()?;
+        let mut previous = class(first);
+        // whether the cluster so far is a pictographic base followed by extends
+        let mut emoji = previous == Class::Pictographic;
+        let mut regional_indicators = (previous == Class::RegionalIndicator) as usize;
+        let mut end = self.string.len();
+
+        for (index, ch) in chars {
+            let current = class(ch);
+            let join = match (previous, current) {
+                (Class::Cr, Class::Lf) => true,
+                (Class::Cr | Class::Lf | Class::Control, _) => false,
+                (_, Class::Cr | Class::Lf | Class::Control) => false,
+                (Class::L, Class::L | Class::V | Class::Lv | Class::Lvt) => true,
+                (Class::Lv | Class::V, Class::V | Class::T) => true,
+                (Class::Lvt | Class::T, Class::T) => true,
+                (_, Class::Extend | Class::Zwj | Class::SpacingMark) => true,
+                (Class::Prepend, _) => true,
+                (Class::Zwj, Class::Pictographic) => emoji,
+                (Class::RegionalIndicator, Class::RegionalIndicator) => regional_indicators % 2 == 1,
+                _ => false,
+            };
+            if !join {
+                end = index;
+                break;
+            }
+            match current {
+                Class::Pictographic => emoji = true,
+                Class::Extend | Class::Zwj => {}
+                _ => emoji = false,
+            }
+            if current == Class::RegionalIndicator {
+                regional_indicators += 1;
+            }
+            previous = current;
+        }
+
+        let (grapheme, rest) = self.string.split_at(end);
+        self.string = rest;
+        Some(grapheme)
+    }
+}
+
+pub fn graphemes(string: &str) -> Graphemes<'_> {
+    Graphemes { string }
+}
+
+const WIDE: &[(u32, u32)] = &[
+    (0x1100, 0x115F),
+    (0x231A, 0x231B),
+    (0x2329, 0x232A),
+    (0x23E9, 0x23EC),
+    (0x23F0, 0x23F0),
+    (0x23F3, 0x23F3),
+    (0x25FD, 0x25FE),
+    (0x2614, 0x2615),
+    (0x2648, 0x2653),
+    (0x267F, 0x267F),
+    (0x2693, 0x2693),
+    (0x26A1, 0x26A1),
+    (0x26AA, 0x26AB),
+    (0x26BD, 0x26BE),
+    (0x26C4, 0x26C5),
+    (0x26CE, 0x26CE),
+    (0x26D4, 0x26D4),
+    (0x26EA, 0x26EA),
+    (0x26F2, 0x26F3),
+    (0x26F5, 0x26F5),
+    (0x26FA, 0x26FA),
+    (0x26FD, 0x26FD),
+    (0x2705, 0x2705),
+    (0x270A, 0x270B),
+    (0x2728, 0x2728),
+    (0x274C, 0x274C),
+    (0x274E, 0x274E),
+    (0x2753, 0x2755),
+    (0x2757, 0x2757),
+    (0x2795, 0x2797),
+    (0x27B0, 0x27B0),
+    (0x27BF, 0x27BF),
+    (0x2B1B, 0x2B1C),
+    (0x2B50, 0x2B50),
+    (0x2B55, 0x2B55),
+    (0x2E80, 0x303E),
+    (0x3041, 0x33FF),
+    (0x3400, 0x4DBF),
+    (0x4E00, 0x9FFF),
+    (0xA000, 0xA4CF),
+    (0xA960, 0xA97F),
+    (0xAC00, 0xD7A3),
+    (0xF900, 0xFAFF),
+    (0xFE10, 0xFE19),
+    (0xFE30, 0xFE6F),
+    (0xFF00, 0xFF60),
+    (0xFFE0, 0xFFE6),
+    (0x16FE0, 0x18CFF),
+    (0x1B000, 0x1B2FF),
+    (0x1F004, 0x1F004),
+    (0x1F0CF, 0x1F0CF),
+    (0x1F18E, 0x1F18E),
+    (0x1F191, 0x1F19A),
+    (0x1F200, 0x1F251),
+    (0x1F260, 0x1F265),
+    (0x1F300, 0x1F320),
+    (0x1F32D, 0x1F335),
+    (0x1F337, 0x1F37C),
+    (0x1F37E, 0x1F393),
+    (0x1F3A0, 0x1F3CA),
+    (0x1F3CF, 0x1F3D3),
+    (0x1F3E0, 0x1F3F0),
+    (0x1F3F4, 0x1F3F4),
+    (0x1F3F8, 0x1F43E),
+    (0x1F440, 0x1F440),
+    (0x1F442, 0x1F4FC),
+    (0x1F4FF, 0x1F53D),
+    (0x1F54B, 0x1F54E),
+    (0x1F550, 0x1F567),
+    (0x1F57A, 0x1F57A),
+    (0x1F595, 0x1F596),
+    (0x1F5A4, 0x1F5A4),
+    (0x1F5FB, 0x1F64F),
+    (0x1F680, 0x1F6C5),
+    (0x1F6CC, 0x1F6CC),
+    (0x1F6D0, 0x1F6D2),
+    (0x1F6D5, 0x1F6D7),
+    (0x1F6EB, 0x1F6EC),
+    (0x1F6F4, 0x1F6FC),
+    (0x1F7E0, 0x1F7EB),
+    (0x1F90C, 0x1F93A),
+    (0x1F93C, 0x1F945),
+    (0x1F947, 0x1F9FF),
+    (0x1FA70, 0x1FAFF),
+    (0x20000, 0x2FFFD),
+    (0x30000, 0x3FFFD),
+];
+
+/// Characters that are invisible or look like ordinary spaces but change
+/// the meaning of text. ZWJ is left out since it legitimately joins emoji.
+const INVISIBLE: &[(u32, u32, &str)] = &[
+    (0x00A0, 0x00A0, "non-breaking space"),
+    (0x00AD, 0x00AD, "soft hyphen"),
+    (0x061C, 0x061C, "bidi control"),
+    (0x180E, 0x180E, "mongolian vowel separator"),
+    (0x2000, 0x200A, "unusual space"),
+    (0x200B, 0x200C, "zero width character"),
+    (0x200E, 0x200F, "bidi control"),
+    (0x202A, 0x202E, "bidi control"),
+    (0x202F, 0x202F, "non-breaking space"),
+    (0x205F, 0x205F, "unusual space"),
+    (0x2060, 0x2064, "zero width character"),
+    (0x2066, 0x2069, "bidi control"),
+    (0x3164, 0x3164, "hangul filler"),
+    (0xFEFF, 0xFEFF, "zero width no-break space"),
+    (0xFFA0, 0xFFA0, "hangul filler"),
+];
+
+/// What kind of invisible or confusable character `ch` is, if any.
+pub fn invisible(ch: char) -> Option<&'static str> {
+    let c = ch as u32;
+    INVISIBLE
+        .iter()
+        .find(|&&(lo, hi, _)| lo <= c && c <= hi)
+        .map(|&(_, _, kind)| kind)
+}
+
+pub fn invisible_notation(ch: char) -> String {
+    format!("<{:04x}>", ch as u32)
+}
+
+/// Number of terminal columns a grapheme cluster occupies.
+pub fn width(grapheme: &str) -> usize {
+    if grapheme.chars().any(|ch| invisible(ch).is_some()) {
+        let placeholders: usize = grapheme
+            .chars()
+            .filter(|&ch| invisible(ch).is_some())
+            .map(|ch| invisible_notation(ch).len())
+            .sum();
+        let rest: String = grapheme.chars().filter(|&ch| invisible(ch).is_none()).collect();
+        return placeholders + width(&rest);
+    }
+    let mut chars = grapheme.chars();
+    let first = match chars.next() {
+        Some(ch) => ch,
+        None => return 0,
+    };
+    let first_class = class(first);
+    if first_class == Class::Control {
+        return control_notation(grapheme).map_or(0, |notation| notation.len());
+    }
+    if in_table(WIDE, first as u32) {
+        return 2;
+    }
+    if first_class == Class::RegionalIndicator && grapheme.chars().count() > 1 {
+        return 2;
+    }
+    // a text-style pictograph followed by VS16 is drawn in emoji presentation
+    if first_class == Class::Pictographic && grapheme.contains('\u{FE0F}') {
+        return 2;
+    }
+    1
+}
+
+/// Printable stand-in for a control character: caret notation for C0
+/// controls and DEL, and a hex form such as `<9b>` for everything else.
+pub fn control_notation(grapheme: &str) -> Option<String> {
+    let first = grapheme.chars().next()?;
+    if class(first) != Class::Control || first == '\n' {
+        return None;
+    }
+    let c = first as u32;
+    Some(match c {
+        0x00..=0x1F => format!("^{}", (c as u8 + b'@') as char),
+        0x7F => String::from("^?"),
+        _ => format!("<{:02x}>", c),
+    })
+}
+
+pub fn str_width(string: &str) -> usize {
+    graphemes(string).map(width).sum()
+}
+
+/// Longest prefix of `string` that fits in `max_width` columns.
+pub fn truncate_to_width(string: &str, max_width: usize) -> &str {
+    let mut total = 0;
+    let mut end = 0;
+    for grapheme in graphemes(string) {
+        total += width(grapheme);
+        if total > max_width {
+            break;
+        }
+        end += grapheme.len();
+    }
+    &string[..end]
+}
+
+/// Rough description of what kind of character `ch` is.
+pub fn describe(ch: char) -> &'static str {
+    if let Some(kind) = invisible(ch) {
+        return kind;
+    }
+    let c = ch as u32;
+    match class(ch) {
+        Class::Cr | Class::Lf | Class::Control => return "control character",
+        Class::Zwj => return "zero width joiner",
+        Class::RegionalIndicator => return "regional indicator",
+        Class::Extend | Class::SpacingMark => return "combining mark",
+        Class::L | Class::V | Class::T => return "hangul jamo",
+        Class::Lv | Class::Lvt => return "hangul syllable",
+        Class::Pictographic => return "emoji",
+        Class::Prepend | Class::Other => {}
+    }
+    match c {
+        0x0370..=0x03FF => "greek letter",
+        0x0400..=0x052F => "cyrillic letter",
+        0x0590..=0x05FF => "hebrew letter",
+        0x0600..=0x06FF => "arabic letter",
+        0x0900..=0x097F => "devanagari letter",
+        0x2000..=0x200B | 0x202F | 0x205F | 0x3000 => "space",
+        0x2010..=0x2027 | 0x2030..=0x205E => "punctuation",
+        0x20A0..=0x20CF => "currency symbol",
+        0x2190..=0x21FF => "arrow",
+        0x2200..=0x22FF => "mathematical operator",
+        0x2500..=0x257F => "box drawing",
+        0x3040..=0x309F => "hiragana",
+        0x30A0..=0x30FF => "katakana",
+        0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0x20000..=0x3FFFD => "CJK ideograph",
+        0xE000..=0xF8FF | 0xF0000..=0x10FFFF => "private use",
+        0xFEFF => "byte order mark",
+        0xFF00..=0xFFEF => "fullwidth form",
+        _ if ch.is_ascii_digit() => "digit",
+        _ if ch.is_ascii_punctuation() => "punctuation",
+        _ if ch.is_whitespace() => "space",
+        _ if ch.is_alphabetic() => "latin letter",
+        _ if ch.is_numeric() => "number",
+        _ => "symbol",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(text: &str) -> Vec<&str> {
+        graphemes(text).collect()
+    }
+
+    #[test]
+    fn combining_marks_stay_with_their_base() {
+        assert_eq!(split("e\u{301}a"), vec!["e\u{301}", "a"]);
+        assert_eq!(split("a\u{323}\u{302}"), vec!["a\u{323}\u{302}"]);
+        // a mark with nothing before it is a cluster of its own
+        assert_eq!(split("\u{301}x"), vec!["\u{301}", "x"]);
+    }
+
+    #[test]
+    fn zwj_sequences_are_one_cluster() {
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        assert_eq!(split(family), vec![family]);
+        let waving = "\u{1F44B}\u{1F3FD}";
+        assert_eq!(split(&format!("{}!", waving)), vec![waving, "!"]);
+        let rainbow_flag = "\u{1F3F3}\u{FE0F}\u{200D}\u{1F308}";
+        assert_eq!(split(rainbow_flag), vec![rainbow_flag]);
+    }
+
+    #[test]
+    fn regional_indicators_pair_up() {
+        let (france, japan) = ("\u{1F1EB}\u{1F1F7}", "\u{1F1EF}\u{1F1F5}");
+        assert_eq!(split(&format!("{}{}", france, japan)), vec![france, japan]);
+        // an odd one out is left alone at the end
+        assert_eq!(split(&format!("{}{}\u{1F1E9}", france, japan)), vec![france, japan, "\u{1F1E9}"]);
+    }
+
+    #[test]
+    fn hangul_jamo_join() {
+        // a precomposed syllable, and the same written as L V T jamo
+        assert_eq!(split("\u{D55C}\u{AE00}"), vec!["\u{D55C}", "\u{AE00}"]);
+        assert_eq!(split("\u{1112}\u{1161}\u{11AB}a"), vec!["\u{1112}\u{1161}\u{11AB}", "a"]);
+        // LV followed by T
+        assert_eq!(split("\u{AC00}\u{11A8}"), vec!["\u{AC00}\u{11A8}"]);
+    }
+
+    #[test]
+    fn cr_lf_is_one_cluster() {
+        assert_eq!(split("a\r\nb"), vec!["a", "\r\n", "b"]);
+        assert_eq!(split("\n\r"), vec!["\n", "\r"]);
+        // nothing extends a control
+        assert_eq!(split("\r\u{301}"), vec!["\r", "\u{301}"]);
+    }
+
+    #[test]
+    fn cluster_widths() {
+        assert_eq!(width("e\u{301}"), 1);
+        assert_eq!(width("\u{1F1EB}\u{1F1F7}"), 2);
+        assert_eq!(width("\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}"), 2);
+        assert_eq!(width("\u{D55C}"), 2);
+        assert_eq!(width("\u{2764}\u{FE0F}"), 2);
+    }
+}
@@ -0,0 +1,202 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long the file has to stay unchanged before it's reported, so a
+/// build rewriting it several times in a row only triggers one reload.
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+/// How often the file is looked at otherwise.
+const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+pub enum Change {
+    Modified,
+    Missing(Missing),
+}
+
+/// Why the file can't be looked at any more.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Missing {
+    Deleted,
+    /// It's still there, but can no longer be read.
+    NoPermission,
+    /// The directory it was in went away with it.
+    DirectoryGone,
+}
+
+impl Missing {
+    /// The marker for the status bar.
+    pub fn marker(self) -> &'static str {
+        match self {
+            Missing::Deleted => "[file deleted]",
+            Missing::NoPermission => "[permission denied]",
+            Missing::DirectoryGone => "[directory deleted]",
+        }
+    }
+}
+
+/// Notices when the open file is rewritten or removed by comparing its
+/// modification time and size each time it's polled.
+pub struct Watch {
+    path: PathBuf,
+    stamp: Option<(SystemTime, u64)>,
+    /// Set while the file can't be looked at; only once it has been seen,
+    /// so a file that never existed isn't reported as deleted.
+    missing: Option<Missing>,
+    seen: bool,
+    /// Whether the file's permissions keep it from being written, as of
+    /// the last look, for the status bar's [RO].
+    readonly: bool,
+    changed_at: Option<Instant>,
+    next_check: Instant,
+}
+
+impl Watch {
+    pub fn new(path: PathBuf) -> Self {
+        let (stamp, readonly) = match Self::stamp(&path) {
+            Ok((stamp, readonly)) => (Some(stamp), readonly),
+            Err(_) => (None, false),
+        };
+        Self {
+            path,
+            stamp,
+            missing: None,
+            seen: stamp.is_some(),
+            readonly,
+            changed_at: None,
+            next_check: Instant::now() + CHECK_INTERVAL,
+        }
+    }
+
+    /// The modification time and size, and whether it's read-only.
+    fn stamp(path: &Path) -> Result<((SystemTime, u64), bool), Missing> {
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(error) => return Err(Self::why_missing(path, &error)),
+        };
+        // losing read permission on the file itself leaves its metadata
+        // readable, so it takes an open to notice
+        if metadata.is_file() {
+            if let Err(error) = File::open(path) {
+                return Err(Self::why_missing(path, &error));
+            }
+        }
+        let stamp = (metadata.modified().map_err(|_| Missing::NoPermission)?, metadata.len());
+        Ok((stamp, metadata.permissions().readonly()))
+    }
+
+    fn why_missing(path: &Path, error: &io::Error) -> Missing {
+        if error.kind() == io::ErrorKind::PermissionDenied {
+            return Missing::NoPermission;
+        }
+        let parent = match path.parent() {
+            Some(parent) if parent.as_os_str().is_empty() => Path::new("."),
+            Some(parent) => parent,
+            None => return Missing::Deleted,
+        };
+        match fs::metadata(parent) {
+            Ok(metadata) if metadata.is_dir() => Missing::Deleted,
+            Err(error) if error.kind() == io::ErrorKind::PermissionDenied => Missing::NoPermission,
+            _ => Missing::DirectoryGone,
+        }
+    }
+
+    /// Why the file has gone, while it has.
+    pub fn missing(&self) -> Option<Missing> {
+        self.missing
+    }
+
+    pub fn readonly(&self) -> bool {
+        self.readonly
+    }
+
+    pub fn time_left(&self) -> Duration {
+        self.next_check.saturating_duration_since(Instant::now())
+    }
+
+    /// Looks at the file if it's time to. Changes are reported once the
+    /// file has settled; the file going missing straight away, and coming
+    /// back as a change.
+    pub fn poll(&mut self) -> Option<Change> {
+        if self.time_left() > Duration::ZERO {
+            return None;
+        }
+        let change = self.check();
+        let interval = if self.changed_at.is_some() { SETTLE_TIME } else { CHECK_INTERVAL };
+        self.next_check = Instant::now() + interval;
+        change
+    }
+
+    fn check(&mut self) -> Option<Change> {
+        let stamp = match Self::stamp(&self.path) {
+            Ok((stamp, readonly)) => {
+                self.readonly = readonly;
+                stamp
+            }
+            Err(_) if !self.seen => return None,
+            Err(missing) => {
+                self.stamp = None;
+                self.changed_at = None;
+                if self.missing == Some(missing) {
+                    return None;
+                }
+                self.missing = Some(missing);
+                return Some(Change::Missing(missing));
+            }
+        };
+        // a file that comes back is reloaded even if it looks the same,
+        // since a rename back can keep its old time and size
+        if self.missing.take().is_some() || Some(stamp) != self.stamp {
+            self.stamp = Some(stamp);
+            self.seen = true;
+            self.changed_at = Some(Instant::now());
+        }
+        match self.changed_at {
+            Some(time) if time.elapsed() >= SETTLE_TIME => {
+                self.changed_at = None;
+                Some(Change::Modified)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sleeps_until_the_next_check() {
+        let path = std::env::temp_dir().join(format!("rte-watch-{}", std::process::id()));
+        fs::write(&path, "a").unwrap();
+        let mut watch = Watch::new(path.clone());
+        let left = watch.time_left();
+        assert!(left > CHECK_INTERVAL - Duration::from_millis(500) && left <= CHECK_INTERVAL);
+        // polling early doesn't look at the file or move the deadline
+        fs::remove_file(&path).unwrap();
+        assert!(watch.poll().is_none());
+        assert!(watch.time_left() <= left);
+        watch.next_check = Instant::now();
+        assert!(matches!(watch.poll(), Some(Change::Missing(Missing::Deleted))));
+        assert!(watch.time_left() > Duration::ZERO);
+    }
+
+    #[test]
+    fn notices_permissions() {
+        let path = std::env::temp_dir().join(format!("rte-watch-readonly-{}", std::process::id()));
+        fs::write(&path, "a").unwrap();
+        let mut watch = Watch::new(path.clone());
+        assert!(!watch.readonly());
+        let mut permissions = fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&path, permissions.clone()).unwrap();
+        watch.next_check = Instant::now();
+        // a chmod changes neither the time nor the size: no reload
+        assert!(watch.poll().is_none());
+        assert!(watch.readonly());
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        fs::set_permissions(&path, permissions).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+}